@@ -0,0 +1,282 @@
+//! `#[derive(Flags)]` for [flag-parser](https://crates.io/crates/flag-parser)
+//!
+//! Each field of the struct is a flag named after the field. The flag is written
+//! `--field-name` unless told otherwise, and what it takes depends on the type
+//! of the field:
+//!
+//! - `bool` is a switch, `true` when the flag is given, unless `--no-name` or
+//!   `--name=false` comes after it; `default = "true"` turns it on by default
+//! - `Option<T>` takes a value and is `None` when the flag isn't given
+//! - `Vec<T>` takes a value and gets every value given
+//! - any other `T` takes a value, and the flag must be given unless it has a default
+//!
+//! Values are converted with `FromStr`. Doc comments become the help text of
+//! the flag. `#[flag(...)]` tunes a field:
+//!
+//! - `short` or `short = 'c'`: also written `-c`, the first letter of the field by default
+//! - `long = "name"`: written `--name` instead
+//! - `alias = "name"`: also written `--name`
+//! - `default = "value"`: the value when the flag isn't given
+//! - `help = "text"`: the help text, instead of the doc comment
+//! - `value_name = "NAME"`: how the value is named in help text
+//! - `env = "NAME"`: read from the environment variable `NAME` when not given
+//! - `conflicts_with = "field"`: can't be given together with `field`
+//! - `requires = "field"`: can't be given without `field`
+//! - `count`: a switch counting how many times it is given, for integer fields
+//!
+//! `#[flags(name = "mytool")]` on the struct names the command, which is the
+//! struct name in kebab-case by default, and `#[flags(env_prefix = "MYTOOL")]`
+//! reads every flag from an environment variable like `MYTOOL_PORT` when it
+//! isn't given. `#[flags(allow_prefixes)]` accepts unambiguous prefixes of long
+//! flags. The doc comment of the struct describes the command in help text.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, LitChar, LitStr, PathArguments, Type};
+
+#[proc_macro_derive(Flags, attributes(flags, flag))]
+pub fn derive_flags(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    let ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(ident, "#[derive(Flags)] needs a struct with named fields")),
+        },
+        _ => return Err(Error::new_spanned(ident, "#[derive(Flags)] needs a struct")),
+    };
+
+    let mut name = kebab_case(&ident.to_string());
+    let mut env_prefix = None;
+    let mut prefixes = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("flags")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("allow_prefixes") {
+                prefixes = true;
+            } else if meta.path.is_ident("env_prefix") {
+                env_prefix = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("unknown flags attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    let env_prefix = env_prefix.into_iter();
+    let prefixes = prefixes.then(|| quote!(.allow_prefixes()));
+
+    let about = doc_comment(&input.attrs).into_iter();
+    let fields = fields.iter().map(Field::new).collect::<Result<Vec<_>, _>>()?;
+    let specs = fields.iter().map(Field::spec);
+    let values = fields.iter().enumerate().map(|(i, field)| field.value(i));
+
+    Ok(quote! {
+        impl ::flag_parser::Flags for #ident {
+            fn spec() -> ::flag_parser::Spec {
+                ::flag_parser::Spec::new(#name)
+                    #(.about(#about))*
+                    #(.env_prefix(#env_prefix))*
+                    #prefixes
+                    #(.flag(#specs))*
+            }
+
+            fn from_parsed(parsed: &::flag_parser::Parsed) -> ::core::result::Result<Self, ::flag_parser::ParseError> {
+                let spec = <Self as ::flag_parser::Flags>::spec();
+                let _ = &spec;
+                ::core::result::Result::Ok(#ident { #(#values),* })
+            }
+        }
+    })
+}
+
+/// What a field holds
+enum Kind {
+    Switch,
+    Count,
+    Optional(Type),
+    Many(Type),
+    Required(Type),
+}
+
+struct Field {
+    ident: syn::Ident,
+    name: String,
+    kind: Kind,
+    shorts: Vec<char>,
+    longs: Vec<String>,
+    aliases: Vec<String>,
+    default: Option<String>,
+    help: Option<String>,
+    value_name: Option<String>,
+    env: Option<String>,
+    conflicts: Vec<String>,
+    requires: Vec<String>,
+}
+
+impl Field {
+    fn new(field: &syn::Field) -> Result<Field, Error> {
+        let ident = field.ident.clone().expect("named fields have names");
+        let name = ident.to_string().trim_start_matches("r#").to_string();
+        let mut count = false;
+        let mut shorts = Vec::new();
+        let mut longs = Vec::new();
+        let mut aliases = Vec::new();
+        let mut default = None;
+        let mut help = doc_comment(&field.attrs);
+        let mut value_name = None;
+        let mut env = None;
+        let mut conflicts = Vec::new();
+        let mut requires = Vec::new();
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("flag")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("short") {
+                    shorts.push(match meta.value() {
+                        Ok(value) => value.parse::<LitChar>()?.value(),
+                        Err(_) => name.chars().next().expect("fields have names"),
+                    });
+                } else if meta.path.is_ident("long") {
+                    longs.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    default = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("help") {
+                    help = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("value_name") {
+                    value_name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("env") {
+                    env = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("conflicts_with") {
+                    conflicts.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("requires") {
+                    requires.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("count") {
+                    count = true;
+                } else {
+                    return Err(meta.error("unknown flag attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if longs.is_empty() {
+            longs.push(name.replace('_', "-"));
+        }
+
+        let kind = match (count, inner_type(&field.ty, "Option"), inner_type(&field.ty, "Vec")) {
+            (true, _, _) => Kind::Count,
+            (false, Some(ty), _) => Kind::Optional(ty),
+            (false, None, Some(ty)) => Kind::Many(ty),
+            _ if is_bool(&field.ty) => Kind::Switch,
+            _ => Kind::Required(field.ty.clone()),
+        };
+        if default.is_some() && !matches!(kind, Kind::Required(_) | Kind::Switch) {
+            return Err(Error::new_spanned(&field.ty, "only `bool` and plain fields can have a default"));
+        }
+
+        Ok(Field { ident, name, kind, shorts, longs, aliases, default, help, value_name, env, conflicts, requires })
+    }
+
+    /// The `FlagSpec` declaring the field
+    fn spec(&self) -> TokenStream {
+        let Field { name, shorts, longs, aliases, conflicts, requires, .. } = self;
+        let takes_value = match self.kind {
+            Kind::Switch | Kind::Count => quote!(),
+            _ => quote!(.takes_value()),
+        };
+        let required = match self.kind {
+            Kind::Required(_) if self.default.is_none() => quote!(.required()),
+            _ => quote!(),
+        };
+        let help = self.help.iter();
+        let default = self.default.iter();
+        let value_name = self.value_name.iter();
+        let env = self.env.iter();
+
+        quote! {
+            ::flag_parser::FlagSpec::new(#name)
+                #(.short(#shorts))*
+                #(.long(#longs))*
+                #(.alias(#aliases))*
+                #takes_value
+                #required
+                #(.help(#help))*
+                #(.default_value(#default))*
+                #(.value_name(#value_name))*
+                #(.env(#env))*
+                #(.conflicts_with(#conflicts))*
+                #(.requires(#requires))*
+        }
+    }
+
+    /// The value of the field, taken from `parsed`, where the field's spec is
+    /// the `i`th flag of `spec`
+    fn value(&self, i: usize) -> TokenStream {
+        let Field { ident, name, .. } = self;
+        let value = match &self.kind {
+            Kind::Switch => quote!(::flag_parser::__switch(parsed, &spec.flags()[#i])),
+            Kind::Count => quote!(::flag_parser::__count(parsed, &spec.flags()[#i])?),
+            Kind::Optional(ty) => quote!(parsed.get::<#ty>(#name)?),
+            Kind::Many(ty) => quote!(parsed.get_many::<#ty>(#name)?),
+            Kind::Required(ty) => quote!(::flag_parser::__required::<#ty>(parsed, &spec.flags()[#i])?),
+        };
+        quote!(#ident: #value)
+    }
+}
+
+/// The first paragraph of the doc comments in `attrs`, on a single line
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+        if let syn::Meta::NameValue(syn::MetaNameValue {
+            value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(text), .. }),
+            ..
+        }) = &attr.meta
+        {
+            let text = text.value();
+            match text.trim() {
+                "" if lines.is_empty() => {}
+                "" => break,
+                line => lines.push(line.to_string()),
+            }
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// `T` when `ty` is `wrapper<T>`
+fn inner_type(ty: &Type, wrapper: &str) -> Option<Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            kebab.push('-');
+        }
+        kebab.extend(c.to_lowercase());
+    }
+    kebab
+}
@@ -0,0 +1,150 @@
+use flag_parser::{Flags, ParseError, Span, Violation};
+use std::path::PathBuf;
+
+/// Does things to files
+#[derive(Flags, Debug, PartialEq)]
+#[flags(name = "mytool", env_prefix = "MYTOOL")]
+struct Args {
+    /// Print more
+    ///
+    /// Give it more than once to print even more.
+    #[flag(short, count)]
+    verbose: u8,
+    /// Port to listen on
+    #[flag(short = 'p', default = "8080")]
+    port: u16,
+    #[flag(short, alias = "out", value_name = "FILE")]
+    output: Option<PathBuf>,
+    #[flag(short = 'I', long = "include")]
+    includes: Vec<String>,
+    dry_run: bool,
+}
+
+#[derive(Flags, Debug, PartialEq)]
+struct ServerConfig {
+    #[flag(env = "SERVER_HOST")]
+    host: String,
+}
+
+#[test]
+fn spec() {
+    let spec = Args::spec();
+    let port = spec.get("port").unwrap();
+
+    assert_eq!(spec.name(), "mytool");
+    assert_eq!(port.shorts(), &['p']);
+    assert_eq!(port.longs(), &["port".to_string()]);
+    assert_eq!(port.default(), Some("8080"));
+    assert_eq!(port.help_text(), Some("Port to listen on"));
+    assert_eq!(spec.get("verbose").unwrap().help_text(), Some("Print more"));
+    assert!(!spec.get("verbose").unwrap().needs_value());
+    assert_eq!(spec.get("dry_run").unwrap().longs(), &["dry-run".to_string()]);
+    assert_eq!(spec.get("includes").unwrap().shorts(), &['I']);
+    assert_eq!(ServerConfig::spec().name(), "server-config");
+}
+
+#[test]
+fn help() {
+    let help = Args::spec().help_with_width(80);
+
+    assert!(help.starts_with("Does things to files\n\nUsage: mytool [OPTIONS]\n"));
+    assert!(help.contains("  -p, --port <PORT>           Port to listen on [default: 8080]\n"));
+    assert!(help.contains("  -o, --output <FILE>\n"));
+    assert!(matches!(Args::parse("--help"), Err(ParseError::HelpRequested { .. })));
+}
+
+#[test]
+fn values() {
+    let args = Args::parse("-vv --out out.txt -I src --include=lib --dry-run -p 80").unwrap();
+
+    assert_eq!(args, Args {
+        verbose: 2,
+        port: 80,
+        output: Some(PathBuf::from("out.txt")),
+        includes: vec!["src".to_string(), "lib".to_string()],
+        dry_run: true,
+    });
+}
+
+fn no_env(_: &str) -> Option<String> {
+    None
+}
+
+#[test]
+fn defaults() {
+    let args = Args::parse_args_with_env(Vec::<String>::new(), &no_env).unwrap();
+
+    assert_eq!(args, Args { verbose: 0, port: 8080, output: None, includes: vec![], dry_run: false });
+}
+
+#[test]
+fn errors() {
+    assert_eq!(Args::parse_with_env("--port http", &no_env).unwrap_err(), ParseError::InvalidValue {
+        flag: "--port".to_string(),
+        value: "http".to_string(),
+        reason: "invalid digit found in string".to_string(),
+        span: Span::new(7, 11),
+    });
+    assert!(matches!(Args::parse_with_env("--verbose=2", &no_env), Err(ParseError::UnexpectedValue { .. })));
+    assert_eq!(Args::parse_with_env(&"-v ".repeat(255), &no_env).unwrap().verbose, 255);
+    assert_eq!(Args::parse_with_env(&"-v ".repeat(256), &no_env).unwrap_err(), ParseError::InvalidValue {
+        flag: "--verbose".to_string(),
+        value: "256".to_string(),
+        reason: "given too many times".to_string(),
+        span: Span::new(766, 767),
+    });
+    assert_eq!(ServerConfig::parse_with_env("", &no_env).unwrap_err(), ParseError::Violations {
+        violations: vec![Violation::Missing { flag: "--host".to_string() }],
+        span: Span::default(),
+    });
+    assert!(ServerConfig::spec().help_with_width(80).contains("--host <HOST>    [required]\n"));
+    assert_eq!(ServerConfig::parse_with_env("--host localhost", &no_env).unwrap(), ServerConfig {
+        host: "localhost".to_string()
+    });
+}
+
+#[test]
+fn env() {
+    let env = |name: &str| match name {
+        "MYTOOL_PORT" => Some("9000".to_string()),
+        "MYTOOL_DRY_RUN" => Some("1".to_string()),
+        "SERVER_HOST" => Some("example.com".to_string()),
+        _ => None,
+    };
+    let args = Args::parse_with_env("-p 80", &env).unwrap();
+
+    assert_eq!((args.port, args.dry_run), (80, true));
+    assert_eq!(Args::parse_with_env("", &env).unwrap().port, 9000);
+    assert_eq!(ServerConfig::parse_with_env("", &env).unwrap().host, "example.com");
+}
+
+#[derive(Flags, Debug, PartialEq)]
+#[flags(allow_prefixes)]
+struct Output {
+    #[flag(default = "true")]
+    color: bool,
+    #[flag(conflicts_with = "verbose")]
+    quiet: bool,
+    #[flag(requires = "color")]
+    verbose: bool,
+}
+
+#[test]
+fn switches() {
+    let output = Output::parse_with_env("", &|_: &str| None).unwrap();
+    assert_eq!(output, Output { color: true, quiet: false, verbose: false });
+    assert_eq!(Output::parse("--no-color --quiet=yes").unwrap(), Output { color: false, quiet: true, verbose: false });
+    let output = Output::parse("--color=false --quiet --color --no-quiet").unwrap();
+    assert_eq!(output, Output { color: true, quiet: false, verbose: false });
+    assert!(Output::from_parsed(&Output::spec().parse("").unwrap()).unwrap().color);
+}
+
+#[test]
+fn rules() {
+    assert_eq!(
+        Output::parse("--quiet --verbose --no-color").unwrap_err().to_string(),
+        "`--quiet` can't be given with `--verbose`\n`--verbose` requires `--color`"
+    );
+    assert!(Output::parse("--verbose").unwrap().verbose);
+    assert!(Output::parse("--verb").unwrap().verbose);
+}
@@ -0,0 +1,335 @@
+use crate::{FlagSpec, Spec};
+use std::fmt;
+use std::str::FromStr;
+
+/// A shell that [`Spec::completion`] writes scripts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Shell, String> {
+        match name {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("unknown shell `{}`, expected bash, zsh or fish", name)),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        })
+    }
+}
+
+impl Spec {
+    /// A script that makes `shell` complete the flags, subcommands and value
+    /// choices of the command
+    ///
+    /// Values of flags without choices are completed as file names.
+    ///
+    /// ```Rust
+    /// // mytool completions bash > /etc/bash_completion.d/mytool
+    /// print!("{}", spec.completion(Shell::Bash));
+    /// ```
+    pub fn completion(&self, shell: Shell) -> String {
+        let mut levels = Vec::new();
+        collect_levels(self, &mut Vec::new(), &mut levels);
+        match shell {
+            Shell::Bash => bash(self, &levels),
+            Shell::Zsh => zsh(self, &levels),
+            Shell::Fish => fish(self, &levels),
+        }
+    }
+}
+
+/// Every command and subcommand of a spec, with the names of the subcommands
+/// leading to it
+fn collect_levels<'s>(spec: &'s Spec, path: &mut Vec<&'s str>, levels: &mut Vec<(Vec<&'s str>, &'s Spec)>) {
+    levels.push((path.clone(), spec));
+    for subcommand in spec.subcommands() {
+        path.push(subcommand.name());
+        collect_levels(subcommand, path, levels);
+        path.pop();
+    }
+}
+
+/// Every way a flag can be written, like `-o` and `--output`
+fn written(flag: &FlagSpec) -> Vec<String> {
+    let shorts = flag.shorts().iter().map(|short| format!("-{}", short));
+    let longs = flag.longs().iter().chain(flag.aliases()).map(|long| format!("--{}", long));
+    shorts.chain(longs).collect()
+}
+
+/// Quotes `text` for any of the supported shells
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// The name of the shell function completing `spec`
+fn function_name(spec: &Spec) -> String {
+    let name: String = spec.name().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    format!("_{}", name)
+}
+
+/// The `case` patterns selecting a subcommand from the one before it
+fn subcommand_cases(levels: &[(Vec<&str>, &Spec)]) -> String {
+    let mut cases = String::new();
+    for (path, spec) in levels {
+        for subcommand in spec.subcommands() {
+            let key = quote(&format!("{}:{}", path.join(" "), subcommand.name()));
+            let mut next = path.clone();
+            next.push(subcommand.name());
+            cases += &format!("            {}) cmd={} ;;\n", key, quote(&next.join(" ")));
+        }
+    }
+    cases
+}
+
+/// The `case` patterns completing the values of flags, using `choices` for
+/// flags with choices and `files` for the others
+fn value_cases(levels: &[(Vec<&str>, &Spec)], choices: impl Fn(&str) -> String, files: &str) -> String {
+    let mut cases = String::new();
+    for (path, spec) in levels {
+        for flag in spec.flags().iter().filter(|flag| flag.needs_value()) {
+            let key = |name: &String| quote(&format!("{}:{}", path.join(" "), name));
+            let keys: Vec<String> = written(flag).iter().map(key).collect();
+            let complete = match flag.allowed_values() {
+                [] => files.to_string(),
+                values => choices(&values.iter().map(|value| quote(value)).collect::<Vec<_>>().join(" ")),
+            };
+            cases += &format!("        {}) {}; return ;;\n", keys.join("|"), complete);
+        }
+    }
+    cases
+}
+
+/// The `case` patterns listing the words to complete at each level
+fn word_cases(levels: &[(Vec<&str>, &Spec)], list: impl Fn(&str) -> String) -> String {
+    let mut cases = String::new();
+    for (path, spec) in levels {
+        let mut words: Vec<String> = spec.flags_with_help().iter().flat_map(written).collect();
+        words.extend(spec.subcommands().iter().map(|subcommand| subcommand.name().to_string()));
+        let words: Vec<String> = words.iter().map(|word| quote(word)).collect();
+        cases += &format!("        {}) {} ;;\n", quote(&path.join(" ")), list(&words.join(" ")));
+    }
+    cases
+}
+
+fn bash(spec: &Spec, levels: &[(Vec<&str>, &Spec)]) -> String {
+    let function = function_name(spec);
+    format!(
+        r#"{function}() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local cmd="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "$cmd:${{COMP_WORDS[i]}}" in
+{subcommands}        esac
+    done
+
+    case "$cmd:$prev" in
+{values}    esac
+
+    local words=()
+    case "$cmd" in
+{words}    esac
+    COMPREPLY=($(compgen -W "${{words[*]}}" -- "$cur"))
+}}
+
+complete -F {function} {name}
+"#,
+        function = function,
+        name = quote(spec.name()),
+        subcommands = subcommand_cases(levels),
+        values = value_cases(
+            levels,
+            |values| format!(r#"COMPREPLY=($(compgen -W "{}" -- "$cur"))"#, values),
+            r#"COMPREPLY=($(compgen -f -- "$cur"))"#,
+        ),
+        words = word_cases(levels, |words| format!("words=({})", words)),
+    )
+}
+
+fn zsh(spec: &Spec, levels: &[(Vec<&str>, &Spec)]) -> String {
+    let function = function_name(spec);
+    format!(
+        r#"#compdef {command}
+
+{function}() {{
+    local cmd="" i
+    for ((i = 2; i < CURRENT; i++)); do
+        case "$cmd:${{words[i]}}" in
+{subcommands}        esac
+    done
+
+    case "$cmd:${{words[CURRENT-1]}}" in
+{values}    esac
+
+    case "$cmd" in
+{words}    esac
+}}
+
+if [ "$funcstack[1]" = "{function}" ]; then
+    {function} "$@"
+else
+    compdef {function} {name}
+fi
+"#,
+        function = function,
+        // zsh reads the `#compdef` line itself, without unquoting it
+        command = spec.name(),
+        name = quote(spec.name()),
+        subcommands = subcommand_cases(levels),
+        values = value_cases(levels, |values| format!("compadd -- {}", values), "_files"),
+        words = word_cases(levels, |words| format!("compadd -- {}", words)),
+    )
+}
+
+fn fish(spec: &Spec, levels: &[(Vec<&str>, &Spec)]) -> String {
+    let name = quote(spec.name());
+    let mut script = format!("complete -c {} -f\n", name);
+    for (path, level) in levels {
+        // the level is selected once its last subcommand is seen, and until one
+        // of its own subcommands is
+        let mut conditions = Vec::new();
+        match path.last() {
+            Some(last) => conditions.push(format!("__fish_seen_subcommand_from {}", quote(last))),
+            None if !level.subcommands().is_empty() => conditions.push("__fish_use_subcommand".to_string()),
+            None => {}
+        }
+        if path.last().is_some() && !level.subcommands().is_empty() {
+            let names: Vec<String> = level.subcommands().iter().map(|subcommand| quote(subcommand.name())).collect();
+            conditions.push(format!("not __fish_seen_subcommand_from {}", names.join(" ")));
+        }
+        let condition = match conditions.is_empty() {
+            true => String::new(),
+            false => format!(" -n {}", quote(&conditions.join("; and "))),
+        };
+
+        for subcommand in level.subcommands() {
+            script += &format!("complete -c {}{} -a {}", name, condition, quote(subcommand.name()));
+            if let Some(about) = subcommand.about_text() {
+                script += &format!(" -d {}", quote(about));
+            }
+            script.push('\n');
+        }
+
+        for flag in level.flags_with_help() {
+            script += &format!("complete -c {}{}", name, condition);
+            for short in flag.shorts() {
+                script += &format!(" -s {}", quote(&short.to_string()));
+            }
+            for long in flag.longs().iter().chain(flag.aliases()) {
+                script += &format!(" -l {}", quote(long));
+            }
+            match flag.allowed_values() {
+                _ if !flag.needs_value() => {}
+                [] => script += " -r -F",
+                values => script += &format!(" -x -a {}", quote(&values.join(" "))),
+            }
+            if let Some(help) = flag.help_text() {
+                script += &format!(" -d {}", quote(help));
+            }
+            script.push('\n');
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .flag(FlagSpec::new("verbose").short('v').long("verbose").help("Print more"))
+            .flag(FlagSpec::new("color").long("color").takes_value().choices(["auto", "always", "never"]))
+            .subcommand(Spec::new("status").about("Show the status").flag(FlagSpec::new("short").short('s')))
+            .subcommand(
+                Spec::new("remote")
+                    .subcommand(Spec::new("add").flag(FlagSpec::new("fetch").short('f').long("fetch")))
+                    .subcommand(Spec::new("remove")),
+            )
+    }
+
+    /// What the bash completion of `spec` offers for `line`, where the last
+    /// word is the one being completed. Needs `bash` on the `PATH`.
+    fn complete(line: &[&str]) -> Vec<String> {
+        let words: Vec<String> = line.iter().map(|word| quote(word)).collect();
+        let script = format!(
+            "{}\nCOMP_WORDS=({})\nCOMP_CWORD={}\n_mytool\nprintf '%s\\n' \"${{COMPREPLY[@]}}\"",
+            spec().completion(Shell::Bash),
+            words.join(" "),
+            line.len() - 1,
+        );
+        let output = Command::new("bash").arg("--norc").arg("-c").arg(script).output().expect("running bash");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        stdout.lines().filter(|line| !line.is_empty()).map(String::from).collect()
+    }
+
+    #[test]
+    fn bash_flags() {
+        assert_eq!(complete(&["mytool", "--"]), vec!["--verbose", "--color", "--help"]);
+        assert_eq!(complete(&["mytool", ""]), vec![
+            "-v", "--verbose", "--color", "-h", "--help", "status", "remote",
+        ]);
+    }
+
+    #[test]
+    fn bash_subcommands() {
+        assert_eq!(complete(&["mytool", "st"]), vec!["status"]);
+        assert_eq!(complete(&["mytool", "-v", "remote", "re"]), vec!["remove"]);
+        assert_eq!(complete(&["mytool", "remote", "add", "-"]), vec!["-f", "--fetch", "-h", "--help"]);
+        assert_eq!(complete(&["mytool", "status", "-"]), vec!["-s", "-h", "--help"]);
+    }
+
+    #[test]
+    fn bash_values() {
+        assert_eq!(complete(&["mytool", "--color", "a"]), vec!["auto", "always"]);
+        assert_eq!(complete(&["mytool", "--color", "n"]), vec!["never"]);
+    }
+
+    #[test]
+    fn zsh() {
+        let script = spec().completion(Shell::Zsh);
+
+        assert!(script.starts_with("#compdef mytool\n"));
+        assert!(script.contains("    compdef _mytool 'mytool'\n"));
+        assert!(script.contains("'remote:add') cmd='remote add' ;;"));
+        assert!(script.contains("':--color') compadd -- 'auto' 'always' 'never'; return ;;"));
+        assert!(script.contains("'remote add') compadd -- '-f' '--fetch' '-h' '--help' ;;"));
+    }
+
+    #[test]
+    fn fish() {
+        let script = spec().completion(Shell::Fish);
+
+        assert!(script.contains("complete -c 'mytool' -n '__fish_use_subcommand' -a 'status' -d 'Show the status'\n"));
+        assert!(script.contains("complete -c 'mytool' -n '__fish_use_subcommand' -l 'color' -x -a 'auto always never'\n"));
+        assert!(script.contains(
+            "complete -c 'mytool' -n '__fish_seen_subcommand_from '\\''remote'\\''; and not \
+             __fish_seen_subcommand_from '\\''add'\\'' '\\''remove'\\''' -a 'add'\n"
+        ));
+    }
+
+    #[test]
+    fn shells() {
+        assert_eq!("zsh".parse(), Ok(Shell::Zsh));
+        assert_eq!(Shell::Fish.to_string(), "fish");
+        assert!("powershell".parse::<Shell>().is_err());
+    }
+}
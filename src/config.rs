@@ -0,0 +1,340 @@
+use crate::spec::parse_bool;
+use crate::{Env, FlagSpec, ParseError, Parsed, Source, Spec, Span};
+use std::ffi::OsString;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Settings read from a config file, used for flags the input doesn't give
+///
+/// The format is a small subset of INI and TOML: `key = value` lines, where the
+/// key is the name or a long name of a flag, grouped in sections naming the
+/// subcommand they apply to.
+///
+/// ```text
+/// # the command itself
+/// port = 8080
+/// include = ["src", "lib"]
+/// dry-run = true
+///
+/// [remote.add]
+/// name = "origin"
+/// ```
+///
+/// Values are bare, quoted with `"` (with `\"`, `\\`, `\n` and `\t` escapes) or
+/// with `'`, or arrays of those. Giving a key more than once gives the flag more
+/// than once. `#` starts a comment, outside of quotes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    name: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    section: Vec<String>,
+    key: String,
+    values: Vec<String>,
+    line: usize,
+}
+
+impl Config {
+    /// Reads the settings in `text`. `name` says where they came from, like the
+    /// path of the file, in errors and in [`Source::Config`].
+    ///
+    /// ```Rust
+    /// let config = Config::parse("mytool.toml", &std::fs::read_to_string("mytool.toml")?)?;
+    /// ```
+    pub fn parse(name: impl Into<String>, text: &str) -> Result<Config, ParseError> {
+        let mut config = Config { name: name.into(), entries: Vec::new() };
+        let mut section = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |reason: &str| config.error(line_number, reason.to_string());
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.split_once(']').map(|(header, _)| header).ok_or_else(|| invalid("expected `]`"))?;
+                section = header.split('.').map(|name| name.trim().to_string()).collect();
+                if section.iter().any(String::is_empty) {
+                    return Err(invalid("empty section name"));
+                }
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected `key = value`"))?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(invalid("empty key"));
+            }
+            let values = values(value.trim()).map_err(invalid)?;
+            config.entries.push(Entry { section: section.clone(), key: key.to_string(), values, line: line_number });
+        }
+        Ok(config)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The values given to the flag in the section `path`, each with where it
+    /// came from
+    pub(crate) fn values(&self, path: &[&str], flag: &FlagSpec) -> Vec<(&str, Source)> {
+        let entries = self.entries.iter().filter(|entry| entry.section.iter().eq(path) && names(flag, &entry.key));
+        let source = |entry: &Entry| Source::Config { name: self.name.clone(), line: entry.line };
+        entries.flat_map(|entry| entry.values.iter().map(move |value| (value.as_str(), source(entry)))).collect()
+    }
+
+    fn error(&self, line: usize, reason: String) -> ParseError {
+        ParseError::InvalidConfig { config: self.name.clone(), line, reason, span: Span::default() }
+    }
+}
+
+impl Spec {
+    /// Like [`Spec::parse_with_env`], with the settings of `configs` between the
+    /// environment and defaults
+    ///
+    /// The input wins over the environment, which wins over the configs, which
+    /// win over defaults. Later configs win over earlier ones, so a user config
+    /// listed after a system one overrides it. [`Parsed::source`] tells which of
+    /// these a value came from.
+    ///
+    /// Every config is checked against the spec first: sections must name
+    /// subcommands, keys must name flags, and values must suit them.
+    pub fn parse_layered<'a>(
+        &self,
+        input: &'a str,
+        configs: &[Config],
+        env: &impl Env,
+    ) -> Result<Parsed<'a>, ParseError> {
+        configs.iter().try_for_each(|config| self.check_config(config))?;
+        let mut parsed = crate::parse_with(input, self)?;
+        self.add_fallbacks(&mut parsed, env, configs, &[], None)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Like [`Spec::parse_args_with_env`], with the layers of [`Spec::parse_layered`]
+    pub fn parse_args_layered<I>(
+        &self,
+        args: I,
+        configs: &[Config],
+        env: &impl Env,
+    ) -> Result<Parsed<'static>, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        configs.iter().try_for_each(|config| self.check_config(config))?;
+        let mut parsed = crate::parse_args_with(args, self)?;
+        self.add_fallbacks(&mut parsed, env, configs, &[], None)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    fn check_config(&self, config: &Config) -> Result<(), ParseError> {
+        for entry in &config.entries {
+            let mut spec = self;
+            for name in &entry.section {
+                let reason = format!("unknown subcommand `[{}]`", entry.section.join("."));
+                spec = spec.get_subcommand(name).ok_or_else(|| config.error(entry.line, reason))?;
+            }
+            let Some(flag) = spec.flags().iter().find(|flag| names(flag, &entry.key)) else {
+                return Err(config.error(entry.line, format!("unknown flag `{}`", entry.key)));
+            };
+            for value in &entry.values {
+                let choices = flag.allowed_values();
+                let reason = if !flag.needs_value() && parse_bool(value).is_none() {
+                    "expected true or false".to_string()
+                } else if flag.needs_value() && !choices.is_empty() && !choices.contains(value) {
+                    format!("expected one of {}", choices.join(", "))
+                } else {
+                    continue;
+                };
+                let reason = format!("invalid value `{}` for `{}`: {}", value, entry.key, reason);
+                return Err(config.error(entry.line, reason));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a config key names the flag
+fn names(flag: &FlagSpec, key: &str) -> bool {
+    flag.name() == key || flag.longs().iter().chain(flag.aliases()).any(|long| long == key)
+}
+
+/// The values of a `key = value` line: one, or every item of an array
+fn values(text: &str) -> Result<Vec<String>, &'static str> {
+    let mut chars = text.chars().peekable();
+    let mut values = Vec::new();
+    if chars.next_if_eq(&'[').is_some() {
+        loop {
+            skip_spaces(&mut chars);
+            if chars.next_if_eq(&']').is_some() {
+                break;
+            }
+            values.push(scalar(&mut chars, &[',', ']'])?);
+            skip_spaces(&mut chars);
+            match chars.next() {
+                Some(',') => {}
+                Some(']') => break,
+                _ => return Err("expected `,` or `]`"),
+            }
+        }
+    } else {
+        values.push(scalar(&mut chars, &[])?);
+    }
+    skip_spaces(&mut chars);
+    match chars.next() {
+        None | Some('#') => Ok(values),
+        Some(_) => Err("unexpected text after the value"),
+    }
+}
+
+/// A single value, quoted or bare. Bare values end at a comment or at one of
+/// `ends`.
+fn scalar(chars: &mut Peekable<Chars>, ends: &[char]) -> Result<String, &'static str> {
+    let mut value = String::new();
+    match chars.peek() {
+        Some('"') => {
+            chars.next();
+            loop {
+                match chars.next().ok_or("unterminated string")? {
+                    '"' => return Ok(value),
+                    '\\' => value.push(match chars.next().ok_or("unterminated string")? {
+                        'n' => '\n',
+                        't' => '\t',
+                        c @ ('"' | '\\') => c,
+                        _ => return Err("unknown escape"),
+                    }),
+                    c => value.push(c),
+                }
+            }
+        }
+        Some('\'') => {
+            chars.next();
+            loop {
+                match chars.next().ok_or("unterminated string")? {
+                    '\'' => return Ok(value),
+                    c => value.push(c),
+                }
+            }
+        }
+        _ => {
+            while let Some(c) = chars.next_if(|&c| c != '#' && !ends.contains(&c)) {
+                value.push(c);
+            }
+            Ok(value.trim_end().to_string())
+        }
+    }
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .env_prefix("MYTOOL")
+            .flag(FlagSpec::new("port").long("port").takes_value().default_value("80"))
+            .flag(FlagSpec::new("include").short('I').long("include").takes_value())
+            .flag(FlagSpec::new("dry-run").long("dry-run"))
+            .flag(FlagSpec::new("color").long("color").takes_value().choices(["auto", "never"]))
+            .subcommand(Spec::new("remote").subcommand(Spec::new("add").flag(FlagSpec::new("name").takes_value())))
+    }
+
+    fn config(name: &str, text: &str) -> Config {
+        Config::parse(name, text).unwrap()
+    }
+
+    #[test]
+    fn values() {
+        let config = config("a.toml", r#"
+            # a comment
+            port = 8080  # another
+            include = ["src", 'a "b"', lib]
+            include = "x\ty"
+
+            [remote.add]
+            name = origin
+        "#);
+        let entries: Vec<_> =
+            config.entries.iter().map(|entry| (entry.key.as_str(), &entry.values, entry.line)).collect();
+
+        assert_eq!(entries, vec![
+            ("port", &vec!["8080".to_string()], 3),
+            ("include", &vec!["src".to_string(), "a \"b\"".to_string(), "lib".to_string()], 4),
+            ("include", &vec!["x\ty".to_string()], 5),
+            ("name", &vec!["origin".to_string()], 8),
+        ]);
+        assert_eq!(config.entries[3].section, vec!["remote", "add"]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |text: &str| Config::parse("a.toml", text).unwrap_err().to_string();
+
+        assert_eq!(error("port"), "a.toml:1: expected `key = value`");
+        assert_eq!(error("\nport = \"80"), "a.toml:2: unterminated string");
+        assert_eq!(error("include = [a b"), "a.toml:1: expected `,` or `]`");
+        assert_eq!(error("port = \"80\" 90"), "a.toml:1: unexpected text after the value");
+        assert_eq!(error("[remote"), "a.toml:1: expected `]`");
+    }
+
+    #[test]
+    fn layers() {
+        let system = config("/etc/mytool.toml", "port = 1\ninclude = a\ndry-run = true");
+        let user = config("~/.mytool.toml", "port = 2");
+        let env = HashMap::from([("MYTOOL_INCLUDE".to_string(), "b".to_string())]);
+        let parsed = spec().parse_layered("--color never", &[system, user], &env).unwrap();
+
+        assert_eq!(parsed.get::<u16>("port"), Ok(Some(2)));
+        assert_eq!(parsed.source("port"), Some(&Source::Config { name: "~/.mytool.toml".to_string(), line: 1 }));
+        assert_eq!(parsed.get_many::<String>("include"), Ok(vec!["b".to_string()]));
+        assert_eq!(parsed.source("include"), Some(&Source::Env("MYTOOL_INCLUDE".to_string())));
+        assert_eq!(parsed.source("color"), Some(&Source::CommandLine));
+        assert!(parsed.contains("dry-run"));
+
+        let parsed = spec().parse_layered("--port 3", &[config("a.toml", "dry-run = false")], &HashMap::new()).unwrap();
+        assert_eq!(parsed.get::<u16>("port"), Ok(Some(3)));
+        assert!(!parsed.contains("dry-run"));
+        assert_eq!(spec().parse_layered("", &[], &HashMap::new()).unwrap().source("port"), Some(&Source::Default));
+    }
+
+    #[test]
+    fn subcommands() {
+        let configs = [config("a.toml", "[remote.add]\nname = origin")];
+        let parsed = spec().parse_layered("remote add", &configs, &HashMap::new()).unwrap();
+
+        assert_eq!(parsed.innermost().value_of("name"), Some("origin"));
+        let parsed = spec().parse_layered("remote", &configs, &HashMap::new()).unwrap();
+        assert_eq!(parsed.innermost().value_of("name"), None);
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let error = |text: &str| spec().parse_layered("", &[config("a.toml", text)], &HashMap::new()).unwrap_err();
+
+        assert_eq!(error("\nverbose = 1"), ParseError::InvalidConfig {
+            config: "a.toml".to_string(),
+            line: 2,
+            reason: "unknown flag `verbose`".to_string(),
+            span: Span::default(),
+        });
+        assert_eq!(error("[remote.rm]\nname = a").to_string(), "a.toml:2: unknown subcommand `[remote.rm]`");
+        assert_eq!(
+            error("dry-run = maybe").to_string(),
+            "a.toml:1: invalid value `maybe` for `dry-run`: expected true or false"
+        );
+        assert_eq!(
+            error("color = always").to_string(),
+            "a.toml:1: invalid value `always` for `color`: expected one of auto, never"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::spec::parse_bool;
+use crate::{Config, Flag, FlagSpec, ParseError, Parsed, Source, Span, Spec, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/// Where environment variables are read from
+///
+/// [`SystemEnv`] reads the real environment. Tests can use a `HashMap` or a
+/// closure instead:
+///
+/// ```Rust
+/// let env = HashMap::from([("MYTOOL_PORT".to_string(), "8080".to_string())]);
+/// let parsed = spec.parse_with_env("--verbose", &env)?;
+/// ```
+pub trait Env {
+    /// The value of the variable, `None` when it isn't set
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// The environment of the process, read with [`std::env::var`]
+///
+/// Variables that aren't valid UTF-8 are taken as not set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnv;
+
+impl Env for SystemEnv {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+impl Env for HashMap<String, String> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+impl<F> Env for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn var(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+impl Spec {
+    /// Like [`Spec::parse`], then reads the flags the input doesn't give from
+    /// their environment variables, or else from their defaults
+    ///
+    /// The input wins over the environment, which wins over defaults. Values
+    /// that don't come from the input end up in [`Parsed::fallbacks`], which
+    /// lookups like [`Parsed::get`] fall back to.
+    ///
+    /// An environment variable set for a flag that takes no value switches it
+    /// on, unless it is empty or false, like `0`, `no` or `false`, which switches
+    /// it off.
+    pub fn parse_with_env<'a>(&self, input: &'a str, env: &impl Env) -> Result<Parsed<'a>, ParseError> {
+        let mut parsed = crate::parse_with(input, self)?;
+        self.add_fallbacks(&mut parsed, env, &[], &[], None)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Like [`Spec::parse_args`], with the fallbacks of [`Spec::parse_with_env`]
+    pub fn parse_args_with_env<I>(&self, args: I, env: &impl Env) -> Result<Parsed<'static>, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        let mut parsed = crate::parse_args_with(args, self)?;
+        self.add_fallbacks(&mut parsed, env, &[], &[], None)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// The environment variable the flag is read from, if any, given the
+    /// prefix a parent command passes down
+    pub(crate) fn env_var(&self, flag: &FlagSpec, inherited: Option<&str>) -> Option<String> {
+        if let Some(name) = flag.env_name() {
+            return Some(name.to_string());
+        }
+        let prefix = self.env_prefix_text().or(inherited)?;
+        Some(format!("{}_{}", prefix, flag.name()).to_uppercase().replace('-', "_"))
+    }
+
+    /// Adds the fallbacks of the flags the input doesn't give, from the first
+    /// layer that has them: the environment, the last config, then defaults
+    pub(crate) fn add_fallbacks(
+        &self,
+        parsed: &mut Parsed,
+        env: &impl Env,
+        configs: &[Config],
+        path: &[&str],
+        inherited: Option<&str>,
+    ) -> Result<(), ParseError> {
+        for flag in self.flags() {
+            if parsed.flags.iter().any(|given| given.name == flag.name()) {
+                continue;
+            }
+            let mut push = |value: Option<&str>, negated: bool, source: Source| {
+                parsed.fallbacks.push(Flag {
+                    name: Cow::Owned(flag.name().to_string()),
+                    written: match &source {
+                        Source::Env(var) => var.clone(),
+                        _ => flag.display_name(),
+                    },
+                    value: value.map(|value| Value::Str(Cow::Owned(value.to_string()))),
+                    index: 0,
+                    span: Span::default(),
+                    value_span: None,
+                    source,
+                    negated,
+                })
+            };
+
+            if let Some((value, var)) = self.env_var(flag, inherited).and_then(|var| Some((env.var(&var)?, var))) {
+                if !flag.needs_value() {
+                    let on = !value.is_empty() && parse_bool(&value) != Some(false);
+                    push(None, !on, Source::Env(var));
+                } else {
+                    check_choice(flag, &value, &var)?;
+                    push(Some(&value), false, Source::Env(var));
+                }
+                continue;
+            }
+            let mut from_configs = configs.iter().rev().map(|config| config.values(path, flag));
+            let from_config = from_configs.find(|values| !values.is_empty());
+            match (from_config, flag.default()) {
+                (Some(mut values), _) if !flag.needs_value() => {
+                    if let Some((value, source)) = values.pop() {
+                        push(None, parse_bool(value) == Some(false), source);
+                    }
+                }
+                (Some(values), _) => values.into_iter().for_each(|(value, source)| push(Some(value), false, source)),
+                (None, Some(default)) if flag.needs_value() => push(Some(default), false, Source::Default),
+                (None, Some(default)) => {
+                    if let Some(on) = parse_bool(default) {
+                        push(None, !on, Source::Default);
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        if let Some(subcommand) = &mut parsed.subcommand {
+            if let Some(spec) = self.get_subcommand(&subcommand.name) {
+                let prefix = self.env_prefix_text().or(inherited);
+                let path: Vec<&str> = path.iter().copied().chain([spec.name()]).collect();
+                spec.add_fallbacks(&mut subcommand.parsed, env, configs, &path, prefix)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_choice(flag: &FlagSpec, value: &str, var: &str) -> Result<(), ParseError> {
+    let choices = flag.allowed_values();
+    match choices.is_empty() || choices.iter().any(|choice| choice == value) {
+        true => Ok(()),
+        false => Err(ParseError::InvalidValue {
+            flag: var.to_string(),
+            value: value.to_string(),
+            reason: format!("expected one of {}", choices.join(", ")),
+            span: Span::default(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .env_prefix("MYTOOL")
+            .flag(FlagSpec::new("port").long("port").takes_value().default_value("80"))
+            .flag(FlagSpec::new("host").long("host").takes_value().env("HOST_NAME"))
+            .flag(FlagSpec::new("dry-run").long("dry-run"))
+            .flag(FlagSpec::new("color").long("color").takes_value().choices(["auto", "never"]))
+            .subcommand(Spec::new("serve").flag(FlagSpec::new("workers").long("workers").takes_value()))
+    }
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn precedence() {
+        let env = env(&[("MYTOOL_PORT", "8080"), ("HOST_NAME", "example.com")]);
+
+        assert_eq!(spec().parse_with_env("--port 1", &env).unwrap().get::<u16>("port"), Ok(Some(1)));
+        assert_eq!(spec().parse_with_env("", &env).unwrap().get::<u16>("port"), Ok(Some(8080)));
+        assert_eq!(spec().parse_with_env("", &HashMap::new()).unwrap().get::<u16>("port"), Ok(Some(80)));
+        assert_eq!(spec().parse_with_env("", &env).unwrap().value_of("host"), Some("example.com"));
+        assert_eq!(spec().parse("").unwrap().value_of("port"), None);
+    }
+
+    #[test]
+    fn sources() {
+        let parsed = spec().parse_with_env("--host h", &env(&[("MYTOOL_PORT", "8080")])).unwrap();
+        let sources: Vec<_> = parsed.fallbacks.iter().map(|flag| (flag.name.as_ref(), &flag.source)).collect();
+
+        assert_eq!(parsed.flags[0].source, Source::CommandLine);
+        assert_eq!(sources, vec![("port", &Source::Env("MYTOOL_PORT".to_string()))]);
+        assert_eq!(parsed.names(), vec!["host", "port"]);
+    }
+
+    #[test]
+    fn switches() {
+        let dry_run = |value: &str| {
+            let value = value.to_string();
+            let env = move |name: &str| (name == "MYTOOL_DRY_RUN").then(|| value.clone());
+            spec().parse_with_env("", &env).unwrap().contains("dry-run")
+        };
+
+        assert!(dry_run("1"));
+        assert!(dry_run("yes"));
+        assert!(!dry_run("0"));
+        assert!(!dry_run("false"));
+        assert!(!dry_run(""));
+    }
+
+    #[test]
+    fn subcommands() {
+        let parsed = spec().parse_with_env("serve", &env(&[("MYTOOL_WORKERS", "4")])).unwrap();
+
+        assert_eq!(parsed.innermost().get::<u8>("workers"), Ok(Some(4)));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let parsed = spec().parse_with_env("", &env(&[("MYTOOL_PORT", "http")])).unwrap();
+
+        assert_eq!(
+            parsed.get::<u16>("port").unwrap_err().to_string(),
+            "invalid value `http` for `MYTOOL_PORT`: invalid digit found in string"
+        );
+        let error = spec().parse_with_env("", &env(&[("MYTOOL_COLOR", "always")])).unwrap_err();
+        assert_eq!(error, ParseError::InvalidValue {
+            flag: "MYTOOL_COLOR".to_string(),
+            value: "always".to_string(),
+            reason: "expected one of auto, never".to_string(),
+            span: Span::default(),
+        });
+    }
+}
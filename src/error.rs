@@ -0,0 +1,118 @@
+use crate::{Span, TokenizeError, Violation};
+use std::fmt;
+
+/// Error returned when an input doesn't match what was expected of it
+///
+/// Flags are written the way they appear in the input, like `--output` or
+/// `-o`, and every span points at the offending text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// A flag that isn't declared, with the declared long flags it is close to,
+    /// closest first
+    UnknownFlag { flag: String, suggestions: Vec<String>, span: Span },
+    /// A long flag written as a prefix of the names of more than one flag,
+    /// listed in `candidates`
+    AmbiguousFlag { flag: String, candidates: Vec<String>, span: Span },
+    /// A flag that takes a value was given none
+    MissingValue { flag: String, span: Span },
+    /// A flag that takes no value was given one, like `--verbose=yes`. The span
+    /// points at the value.
+    UnexpectedValue { flag: String, value: String, span: Span },
+    /// A value that isn't valid for its flag. The span points at the value.
+    InvalidValue { flag: String, value: String, reason: String, span: Span },
+    /// A quote was opened and never closed. The span points at the quote.
+    UnterminatedQuote { quote: char, span: Span },
+    /// A word where a subcommand was expected that doesn't name one
+    UnknownSubcommand { name: String, span: Span },
+    /// `-h` or `--help` was given. Displays as the help text of the command
+    /// or subcommand it was given to, wrapped to 80 columns.
+    HelpRequested { help: String, span: Span },
+    /// A field of a [`Flags`](crate::Flags) type has no value, when it is built
+    /// with [`Flags::from_parsed`](crate::Flags::from_parsed) from results that
+    /// weren't checked against its spec. Parsing against a spec reports missing
+    /// required flags as [`Violation::Missing`] instead. The span is empty.
+    MissingFlag { flag: String, span: Span },
+    /// A config that can't be read or doesn't match the spec, with the line of
+    /// the problem. The span is empty.
+    InvalidConfig { config: String, line: usize, reason: String, span: Span },
+    /// A response file that can't be read or split into words, or that
+    /// includes itself. The span is empty.
+    InvalidResponseFile { path: String, reason: String, span: Span },
+    /// Rules of the spec that the flags given break, every one of them. The
+    /// span is the span of the first.
+    Violations { violations: Vec<Violation>, span: Span },
+}
+
+impl ParseError {
+    /// Where the error is in the input
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnknownFlag { span, .. }
+            | ParseError::AmbiguousFlag { span, .. }
+            | ParseError::MissingValue { span, .. }
+            | ParseError::UnexpectedValue { span, .. }
+            | ParseError::InvalidValue { span, .. }
+            | ParseError::UnterminatedQuote { span, .. }
+            | ParseError::UnknownSubcommand { span, .. }
+            | ParseError::HelpRequested { span, .. }
+            | ParseError::MissingFlag { span, .. }
+            | ParseError::InvalidConfig { span, .. }
+            | ParseError::InvalidResponseFile { span, .. }
+            | ParseError::Violations { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownFlag { flag, suggestions, .. } => {
+                write!(f, "unknown flag `{}`", flag)?;
+                match suggestions.split_last() {
+                    Some((last, [])) => write!(f, ", did you mean `{}`?", last),
+                    Some((last, rest)) => write!(f, ", did you mean `{}` or `{}`?", rest.join("`, `"), last),
+                    None => Ok(()),
+                }
+            }
+            ParseError::AmbiguousFlag { flag, candidates, .. } => {
+                write!(f, "flag `{}` is ambiguous, it could be `{}`", flag, candidates.join("`, `"))
+            }
+            ParseError::MissingValue { flag, .. } => write!(f, "flag `{}` needs a value", flag),
+            ParseError::UnexpectedValue { flag, value, .. } => {
+                write!(f, "flag `{}` takes no value, but was given `{}`", flag, value)
+            }
+            ParseError::InvalidValue { flag, value, reason, .. } => {
+                write!(f, "invalid value `{}` for `{}`: {}", value, flag, reason)
+            }
+            ParseError::UnterminatedQuote { quote, .. } => write!(f, "unterminated {} quote", quote),
+            ParseError::UnknownSubcommand { name, .. } => write!(f, "unknown subcommand `{}`", name),
+            ParseError::HelpRequested { help, .. } => f.write_str(help),
+            ParseError::MissingFlag { flag, .. } => write!(f, "missing required flag `{}`", flag),
+            ParseError::InvalidConfig { config, line, reason, .. } => write!(f, "{}:{}: {}", config, line, reason),
+            ParseError::InvalidResponseFile { path, reason, .. } => write!(f, "response file `{}`: {}", path, reason),
+            ParseError::Violations { violations, .. } => {
+                let messages: Vec<String> = violations.iter().map(Violation::to_string).collect();
+                f.write_str(&messages.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<TokenizeError> for ParseError {
+    fn from(error: TokenizeError) -> ParseError {
+        match error {
+            TokenizeError::UnterminatedQuote { quote, offset } => {
+                ParseError::UnterminatedQuote { quote, span: Span::new(offset, offset + 1) }
+            }
+        }
+    }
+}
+
+impl From<std::convert::Infallible> for ParseError {
+    fn from(error: std::convert::Infallible) -> ParseError {
+        match error {}
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{Config, Env, FlagSpec, ParseError, Parsed, Spec, Span};
+use crate::spec::parse_bool;
+use std::ffi::OsString;
+use std::fmt;
+use std::str::FromStr;
+
+/// A type built from the flags of a command line
+///
+/// Usually implemented with `#[derive(Flags)]`, which needs the `derive`
+/// feature:
+///
+/// ```Rust
+/// #[derive(Flags)]
+/// struct Args {
+///     /// Print more
+///     #[flag(short, count)]
+///     verbose: u8,
+///     /// Port to listen on
+///     #[flag(short = 'p', default = "8080")]
+///     port: u16,
+///     output: Option<PathBuf>,
+/// }
+///
+/// let args = Args::parse("-vv --output out.txt")?;
+/// ```
+pub trait Flags: Sized {
+    /// The flags the type is built from
+    fn spec() -> Spec;
+
+    /// Builds the type from flags parsed against [`Flags::spec`]
+    fn from_parsed(parsed: &Parsed) -> Result<Self, ParseError>;
+
+    /// Parses an input against [`Flags::spec`] and builds the type from it
+    ///
+    /// The environment isn't read: pass [`SystemEnv`](crate::SystemEnv) to
+    /// [`Flags::parse_with_env`] for that.
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        Self::parse_with_env(input, &|_: &str| None)
+    }
+
+    /// Parses arguments that are already split against [`Flags::spec`] and
+    /// builds the type from them, without reading the environment
+    fn parse_args<I>(args: I) -> Result<Self, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        Self::parse_args_with_env(args, &|_: &str| None)
+    }
+
+    /// Like [`Flags::parse`], reading flags the input doesn't give from the
+    /// environment variables in `env`
+    fn parse_with_env(input: &str, env: &impl Env) -> Result<Self, ParseError> {
+        Self::from_parsed(&Self::spec().parse_with_env(input, env)?)
+    }
+
+    /// Like [`Flags::parse_args`], reading environment variables from `env`
+    fn parse_args_with_env<I>(args: I, env: &impl Env) -> Result<Self, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        Self::from_parsed(&Self::spec().parse_args_with_env(args, env)?)
+    }
+
+    /// Like [`Flags::parse_with_env`], with the settings of `configs` between
+    /// the environment and defaults, see [`Spec::parse_layered`]
+    fn parse_layered(input: &str, configs: &[Config], env: &impl Env) -> Result<Self, ParseError> {
+        Self::from_parsed(&Self::spec().parse_layered(input, configs, env)?)
+    }
+
+    /// Like [`Flags::parse_args_with_env`], with the layers of [`Flags::parse_layered`]
+    fn parse_args_layered<I>(args: I, configs: &[Config], env: &impl Env) -> Result<Self, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        Self::from_parsed(&Self::spec().parse_args_layered(args, configs, env)?)
+    }
+}
+
+/// The last value of a flag, or else its default, or else an error saying it is
+/// required. Used by `#[derive(Flags)]`.
+#[doc(hidden)]
+pub fn required<T>(parsed: &Parsed, flag: &FlagSpec) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Some(value) = parsed.get(flag.name())? {
+        return Ok(value);
+    }
+    match flag.default() {
+        Some(default) => default.parse().map_err(|error: T::Err| ParseError::InvalidValue {
+            flag: flag.display_name(),
+            value: default.to_string(),
+            reason: error.to_string(),
+            span: Span::default(),
+        }),
+        None => Err(ParseError::MissingFlag { flag: flag.display_name(), span: Span::default() }),
+    }
+}
+
+/// How many times a flag is given, or an error when that doesn't fit in `T`.
+/// Used by `#[derive(Flags)]`.
+#[doc(hidden)]
+pub fn count<T: TryFrom<usize>>(parsed: &Parsed, flag: &FlagSpec) -> Result<T, ParseError> {
+    let count = parsed.count(flag.name());
+    T::try_from(count).map_err(|_| ParseError::InvalidValue {
+        flag: flag.display_name(),
+        value: count.to_string(),
+        reason: "given too many times".to_string(),
+        span: parsed.occurrences(flag.name()).last().map_or_else(Span::default, |flag| flag.span),
+    })
+}
+
+/// Whether the last occurrence of a switch turns it on, or else its default,
+/// or else `false`. Used by `#[derive(Flags)]`.
+#[doc(hidden)]
+pub fn switch(parsed: &Parsed, flag: &FlagSpec) -> bool {
+    parsed.switch(flag.name()).or_else(|| parse_bool(flag.default()?)).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_values() {
+        let port = FlagSpec::new("port").long("port").takes_value();
+        let spec = Spec::new("mytool").flag(port.clone());
+
+        assert_eq!(required::<u16>(&spec.parse("--port 80").unwrap(), &port), Ok(80));
+        assert_eq!(required::<u16>(&spec.parse("").unwrap(), &port), Err(ParseError::MissingFlag {
+            flag: "--port".to_string(),
+            span: Span::default(),
+        }));
+        assert_eq!(required::<u16>(&spec.parse("").unwrap(), &port.default_value("8080")), Ok(8080));
+    }
+}
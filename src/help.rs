@@ -0,0 +1,247 @@
+use crate::{FlagSpec, Spec};
+
+/// Width of help text when the terminal width isn't known, and of the help in
+/// [`ParseError::HelpRequested`](crate::ParseError::HelpRequested)
+pub(crate) const DEFAULT_WIDTH: usize = 80;
+
+/// Descriptions are never wrapped narrower than this
+const MIN_DESCRIPTION_WIDTH: usize = 20;
+
+impl Spec {
+    /// Help text listing the flags and subcommands of the command, wrapped to
+    /// the width of the terminal
+    ///
+    /// The width is read from the `COLUMNS` environment variable, and is 80
+    /// when that isn't set. Parsing never reads it: the help of `-h` and
+    /// `--help` errors is always 80 columns wide.
+    pub fn help(&self) -> String {
+        let width = std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok());
+        self.help_with_width(width.unwrap_or(DEFAULT_WIDTH))
+    }
+
+    /// Help text listing the flags and subcommands of the command, wrapped to
+    /// `width` columns
+    ///
+    /// ```text
+    /// Usage: mytool [OPTIONS]
+    ///
+    /// Options:
+    ///   -v, --verbose          Print more
+    ///   -o, --output <FILE>    Where to write the output [default: out.txt]
+    ///   -h, --help             Print help
+    /// ```
+    pub fn help_with_width(&self, width: usize) -> String {
+        let mut help = String::new();
+        if let Some(about) = self.about_text() {
+            for line in wrap(about, width) {
+                help += &line;
+                help.push('\n');
+            }
+            help.push('\n');
+        }
+
+        help += &format!("Usage: {} [OPTIONS]", self.command_path());
+        if !self.subcommands().is_empty() {
+            help += " <COMMAND>";
+        }
+        help.push('\n');
+
+        let flags = self.flags_with_help();
+        let has_shorts = flags.iter().any(|flag| !flag.shorts().is_empty());
+        let options: Vec<(String, String)> =
+            flags.iter().map(|flag| (names(flag, has_shorts), description(flag))).collect();
+        help += "\nOptions:\n";
+        help += &table(&options, width);
+
+        if !self.subcommands().is_empty() {
+            let commands: Vec<(String, String)> = self
+                .subcommands()
+                .iter()
+                .map(|subcommand| (subcommand.name().to_string(), subcommand.about_text().unwrap_or("").to_string()))
+                .collect();
+            help += "\nCommands:\n";
+            help += &table(&commands, width);
+        }
+
+        help
+    }
+}
+
+/// How a flag is written in help text, like `-o, --output <FILE>`, indented to
+/// line up with flags that have a short name when `align` is set
+fn names(flag: &FlagSpec, align: bool) -> String {
+    let shorts = flag.shorts().iter().map(|short| format!("-{}", short));
+    let longs = flag.longs().iter().take(1).map(|long| format!("--{}", long));
+    let mut names = shorts.chain(longs).collect::<Vec<_>>().join(", ");
+    if align && flag.shorts().is_empty() {
+        names.insert_str(0, "    ");
+    }
+    if flag.needs_value() {
+        names += &format!(" <{}>", flag.placeholder());
+    }
+    names
+}
+
+pub(crate) fn description(flag: &FlagSpec) -> String {
+    let mut description = flag.help_text().unwrap_or("").to_string();
+    let mut note = |note: String| {
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description += &note;
+    };
+    if let Some(default) = flag.default() {
+        note(format!("[default: {}]", default));
+    } else if flag.is_required() {
+        note("[required]".to_string());
+    }
+    if !flag.allowed_values().is_empty() {
+        note(format!("[possible values: {}]", flag.allowed_values().join(", ")));
+    }
+    description
+}
+
+/// Lays out names and descriptions in two aligned columns, putting the
+/// description of a name too long for the first column on the next line
+fn table(rows: &[(String, String)], width: usize) -> String {
+    let longest = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let column = 2 + longest.min(width / 3) + 4;
+    let description_width = width.saturating_sub(column).max(MIN_DESCRIPTION_WIDTH);
+
+    let mut table = String::new();
+    for (name, description) in rows {
+        table += "  ";
+        table += name;
+        let mut used = 2 + name.chars().count();
+        for line in wrap(description, description_width) {
+            if used + 1 > column {
+                table.push('\n');
+                used = 0;
+            }
+            table += &" ".repeat(column - used);
+            table += &line;
+            table.push('\n');
+            used = 0;
+        }
+        if used > 0 {
+            table.push('\n');
+        }
+    }
+    table
+}
+
+/// Splits text into lines of at most `width` characters, breaking between
+/// words. Words longer than `width` get a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line += word;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseError, Span};
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .about("Does things to files")
+            .flag(FlagSpec::new("verbose").short('v').long("verbose").help("Print more"))
+            .flag(
+                FlagSpec::new("output")
+                    .short('o')
+                    .long("output")
+                    .takes_value()
+                    .value_name("FILE")
+                    .default_value("out.txt")
+                    .help("Where to write the output, created when it doesn't exist"),
+            )
+            .flag(FlagSpec::new("dry-run").long("dry-run"))
+            .flag(FlagSpec::new("color").long("color").takes_value().choices(["auto", "never"]))
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(spec().help_with_width(80), "\
+Does things to files
+
+Usage: mytool [OPTIONS]
+
+Options:
+  -v, --verbose          Print more
+  -o, --output <FILE>    Where to write the output, created when it doesn't
+                         exist [default: out.txt]
+      --dry-run
+      --color <COLOR>    [possible values: auto, never]
+  -h, --help             Print help
+");
+    }
+
+    #[test]
+    fn narrow_help() {
+        let help = spec().help_with_width(40);
+
+        assert!(help.contains("\
+  -v, --verbose    Print more
+  -o, --output <FILE>
+                   Where to write the
+                   output, created when
+                   it doesn't exist
+                   [default: out.txt]
+      --dry-run
+"));
+    }
+
+    #[test]
+    fn subcommand_help() {
+        let spec = Spec::new("git")
+            .subcommand(Spec::new("status").about("Show the working tree status"))
+            .subcommand(Spec::new("remote").subcommand(Spec::new("add").flag(FlagSpec::new("fetch").short('f'))));
+
+        assert!(spec.help_with_width(80).ends_with("\
+Usage: git [OPTIONS] <COMMAND>
+
+Options:
+  -h, --help    Print help
+
+Commands:
+  status    Show the working tree status
+  remote
+"));
+        assert!(spec.subcommands()[1].subcommands()[0].help_with_width(80).starts_with("Usage: git remote add [OPTIONS]\n"));
+    }
+
+    #[test]
+    fn help_flags() {
+        let git = Spec::new("git").subcommand(Spec::new("remote").about("Manage remotes"));
+
+        assert_eq!(spec().parse("-v --help").unwrap_err(), ParseError::HelpRequested {
+            help: spec().help_with_width(80),
+            span: Span::new(3, 9),
+        });
+        assert_eq!(git.parse("remote -h").unwrap_err().to_string(), git.subcommands()[0].help_with_width(80));
+        let ls = Spec::new("ls").flag(FlagSpec::new("human").short('h'));
+        assert!(ls.parse("-h").unwrap().contains("human"));
+        assert_eq!(ls.parse("--help").unwrap_err(), ParseError::HelpRequested {
+            help: ls.help_with_width(80),
+            span: Span::new(0, 6),
+        });
+        let man = Spec::new("man").flag(FlagSpec::new("manual").long("help"));
+        assert!(man.parse("--help").unwrap().contains("manual"));
+        assert!(matches!(man.parse("-h"), Err(ParseError::HelpRequested { .. })));
+        assert_eq!(ls.help_with_width(80), "Usage: ls [OPTIONS]\n\nOptions:\n  -h\n      --help    Print help\n");
+        assert_eq!(man.help_with_width(80), "Usage: man [OPTIONS]\n\nOptions:\n      --help\n  -h            Print help\n");
+    }
+}
@@ -0,0 +1,355 @@
+//! Splits words into flags, values and positionals, without allocating
+//!
+//! [`Lexer`] is the lowest level of the crate: it walks an input once and
+//! yields [`Token`]s that borrow from it. Everything else, [`get_flags`] and
+//! [`parse`] included, is built on it.
+//!
+//! ```Rust
+//! use flag_parser::lexer::{Lexer, Token};
+//!
+//! for token in Lexer::new("-vo out.txt --level=3 -- -x").value_flags(&["o"]) {
+//!     match token? {
+//!         Token::ShortFlag(name) | Token::LongFlag(name) => println!("flag {}", name.unquote()),
+//!         Token::Value(value) => println!("value {}", value.unquote()),
+//!         Token::Positional(word) => println!("positional {}", word.unquote()),
+//!         Token::Terminator(_) => println!("end of flags"),
+//!     }
+//! }
+//! ```
+//!
+//! [`get_flags`]: crate::get_flags
+//! [`parse`]: crate::parse
+
+use crate::tokenizer::{Cursor, Tokenizer, Word};
+use crate::Span;
+
+/// A piece of an input, borrowed from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// A short flag, one grapheme cluster of a cluster like `-abc`
+    ShortFlag(Word<'a>),
+    /// The name of a long flag, without its dashes or `=value`
+    LongFlag(Word<'a>),
+    /// The value of the flag before it
+    Value(Word<'a>),
+    /// A word that isn't a flag, a value or the first `--`, including `-` and
+    /// words like `--=x` that name no flag
+    Positional(Word<'a>),
+    /// The first bare `--`, after which every word is a positional
+    Terminator(Word<'a>),
+}
+
+impl<'a> Token<'a> {
+    /// The text of the token, without dashes for flags
+    pub fn word(&self) -> Word<'a> {
+        match self {
+            Token::ShortFlag(word)
+            | Token::LongFlag(word)
+            | Token::Value(word)
+            | Token::Positional(word)
+            | Token::Terminator(word) => *word,
+        }
+    }
+
+    /// Where the token is in the input
+    pub fn span(&self) -> Span {
+        self.word().span()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum State<'a> {
+    /// Between words
+    Words,
+    /// Inside a short flag cluster, after the last flag yielded
+    Cluster(Cursor<'a>),
+    /// After a long flag written `--name=value`, with its value
+    Inline(Word<'a>),
+    /// After the first `--`
+    Terminated,
+}
+
+/// Iterator over the [`Token`]s of an input
+///
+/// Whether a flag takes a value depends on the flag, so the lexer only yields
+/// [`Token::Value`] for `--name=value` and for the flags listed with
+/// [`Lexer::value_flags`]. Callers that decide as they go can take the value
+/// of a flag just yielded with [`Lexer::value`] instead.
+///
+/// Errors from the words, like an unterminated quote, are passed on.
+#[derive(Debug, Clone)]
+pub struct Lexer<'a, W = Tokenizer<'a>> {
+    words: W,
+    value_flags: &'a [&'a str],
+    state: State<'a>,
+    /// The word the last token came from, with its index
+    word: Option<(usize, Word<'a>)>,
+    taken: usize,
+    wants_value: bool,
+}
+
+impl<'a> Lexer<'a> {
+    /// Lexes an input split into words by the [`tokenizer`](crate::tokenizer)
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer::from_words(Tokenizer::new(input))
+    }
+}
+
+impl<'a, W, E> Lexer<'a, W>
+where
+    W: Iterator<Item = Result<Word<'a>, E>>,
+{
+    /// Lexes words that are already split, such as arguments wrapped in
+    /// [`Word::literal`]
+    pub fn from_words(words: W) -> Lexer<'a, W> {
+        Lexer { words, value_flags: &[], state: State::Words, word: None, taken: 0, wants_value: false }
+    }
+
+    /// Makes the flags named in `names` take the word after them, or the rest of
+    /// their cluster, as a [`Token::Value`]
+    pub fn value_flags(mut self, names: &'a [&'a str]) -> Lexer<'a, W> {
+        self.value_flags = names;
+        self
+    }
+
+    /// Takes the value of the flag just yielded: its `=value`, the rest of its
+    /// short flag cluster, or else the next word, which may look like a flag
+    ///
+    /// `None` when there are no words left.
+    pub fn value(&mut self) -> Option<Result<Word<'a>, E>> {
+        match core::mem::replace(&mut self.state, State::Words) {
+            State::Inline(value) => Some(Ok(value)),
+            State::Cluster(cursor) if cursor.rest().cursor().next_char().is_some() => Some(Ok(cursor.rest())),
+            State::Terminated => {
+                self.state = State::Terminated;
+                self.next_word()
+            }
+            State::Words | State::Cluster(_) => self.next_word(),
+        }
+    }
+
+    /// Takes the value of a long flag just yielded when it is written
+    /// `--name=value`, leaving the next word alone otherwise
+    pub fn inline_value(&mut self) -> Option<Word<'a>> {
+        match self.state {
+            State::Inline(value) => {
+                self.state = State::Words;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// The index of the word the last token came from
+    pub fn index(&self) -> usize {
+        self.word.map_or(0, |(index, _)| index)
+    }
+
+    /// The whole word the last token came from, dashes and all
+    pub fn current_word(&self) -> Option<Word<'a>> {
+        self.word.map(|(_, word)| word)
+    }
+
+    fn next_word(&mut self) -> Option<Result<Word<'a>, E>> {
+        let word = self.words.next()?;
+        self.taken += 1;
+        if let Ok(word) = &word {
+            self.word = Some((self.taken - 1, *word));
+        }
+        Some(word)
+    }
+
+    /// The flag as a token, noting that a value follows when it is a value flag
+    fn flag(&mut self, name: Word<'a>, long: bool) -> Token<'a> {
+        self.wants_value = self.value_flags.iter().any(|value_flag| name.is(value_flag));
+        if long {
+            Token::LongFlag(name)
+        } else {
+            Token::ShortFlag(name)
+        }
+    }
+}
+
+impl<'a, W, E> Iterator for Lexer<'a, W>
+where
+    W: Iterator<Item = Result<Word<'a>, E>>,
+{
+    type Item = Result<Token<'a>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if core::mem::take(&mut self.wants_value) {
+            return self.value().map(|value| value.map(Token::Value));
+        }
+        loop {
+            match core::mem::replace(&mut self.state, State::Words) {
+                State::Inline(value) => return Some(Ok(Token::Value(value))),
+                State::Terminated => {
+                    self.state = State::Terminated;
+                    return self.next_word().map(|word| word.map(Token::Positional));
+                }
+                State::Cluster(mut cursor) => {
+                    let start = cursor.clone();
+                    if cursor.next_grapheme() {
+                        let name = cursor.since(&start);
+                        self.state = State::Cluster(cursor);
+                        return Some(Ok(self.flag(name, false)));
+                    }
+                }
+                State::Words => {
+                    let word = match self.next_word()? {
+                        Ok(word) => word,
+                        Err(error) => return Some(Err(error)),
+                    };
+                    let mut cursor = word.cursor();
+                    if word.is("--") {
+                        self.state = State::Terminated;
+                        return Some(Ok(Token::Terminator(word)));
+                    } else if word.is("-") || cursor.next_char() != Some('-') {
+                        return Some(Ok(Token::Positional(word)));
+                    } else if cursor.clone().next_char() == Some('-') {
+                        cursor.next_char();
+                        let start = cursor.clone();
+                        let mut end = cursor.clone();
+                        while let Some(c) = cursor.next_char() {
+                            if c == '=' {
+                                self.state = State::Inline(cursor.rest());
+                                break;
+                            }
+                            end = cursor.clone();
+                        }
+                        let name = end.since(&start);
+                        if name.is("") {
+                            self.state = State::Words;
+                            return Some(Ok(Token::Positional(word)));
+                        }
+                        return Some(Ok(self.flag(name, true)));
+                    } else {
+                        self.state = State::Cluster(cursor);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenizeError;
+
+    #[cfg(feature = "alloc")]
+    fn tokens<'a>(lexer: Lexer<'a, impl Iterator<Item = Result<Word<'a>, TokenizeError>>>) -> Vec<String> {
+        lexer
+            .map(|token| match token.unwrap() {
+                Token::ShortFlag(name) => format!("-{}", name.unquote()),
+                Token::LongFlag(name) => format!("--{}", name.unquote()),
+                Token::Value(value) => format!("={}", value.unquote()),
+                Token::Positional(word) => word.unquote().into_owned(),
+                Token::Terminator(_) => "|".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn kinds() {
+        let lexer = Lexer::new(r#"build -vx --level=3 --msg "a b" - -- -y --z"#);
+
+        assert_eq!(tokens(lexer), vec!["build", "-v", "-x", "--level", "=3", "--msg", "a b", "-", "|", "-y", "--z"]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn nameless_long_flags() {
+        let lexer = Lexer::new("--=x --= -a").value_flags(&[""]);
+
+        assert_eq!(tokens(lexer), vec!["--=x", "--=", "-a"]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn value_flags() {
+        let lexer = Lexer::new("-vofile -o x --out y --out=z -o --").value_flags(&["o", "out"]);
+
+        assert_eq!(tokens(lexer), vec!["-v", "-o", "=file", "-o", "=x", "--out", "=y", "--out", "=z", "-o", "=--"]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn values_on_demand() {
+        let mut lexer = Lexer::new("-abc d --long=e --f g");
+
+        assert_eq!(lexer.next().unwrap().unwrap().span(), Span::new(1, 2));
+        assert_eq!(lexer.value().unwrap().unwrap().unquote(), "bc");
+        assert_eq!(lexer.next().unwrap().unwrap().word().unquote(), "d");
+        assert_eq!(lexer.next().unwrap().unwrap().word().unquote(), "long");
+        assert_eq!(lexer.inline_value().unwrap().unquote(), "e");
+        assert_eq!(lexer.next().unwrap().unwrap().word().unquote(), "f");
+        assert_eq!(lexer.inline_value(), None);
+        assert_eq!((lexer.value().unwrap().unwrap().unquote(), lexer.index()), ("g".into(), 4));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn without_allocating() {
+        let mut lexer = Lexer::new(r#"-vo "a b" --level=3 -- -x"#).value_flags(&["o"]);
+        let expected = [
+            ("short", "v", Span::new(1, 2)),
+            ("short", "o", Span::new(2, 3)),
+            ("value", r#""a b""#, Span::new(4, 9)),
+            ("long", "level", Span::new(12, 17)),
+            ("value", "3", Span::new(18, 19)),
+            ("terminator", "--", Span::new(20, 22)),
+            ("positional", "-x", Span::new(23, 25)),
+        ];
+
+        for (kind, raw, span) in expected {
+            let token = lexer.next().unwrap().unwrap();
+            let found = match token {
+                Token::ShortFlag(_) => "short",
+                Token::LongFlag(_) => "long",
+                Token::Value(_) => "value",
+                Token::Positional(_) => "positional",
+                Token::Terminator(_) => "terminator",
+            };
+            assert_eq!((found, token.word().raw(), token.span()), (kind, raw, span));
+        }
+        assert!(lexer.next().is_none());
+        assert!(Lexer::new(r#"--msg="a b""#).nth(1).unwrap().unwrap().word().is("a b"));
+    }
+
+    #[test]
+    fn spans_and_indices() {
+        let mut lexer = Lexer::new("x  --name=va -ab");
+        let mut found = Vec::new();
+        while let Some(token) = lexer.next() {
+            found.push((token.unwrap().span(), lexer.index()));
+        }
+
+        assert_eq!(found, vec![
+            (Span::new(0, 1), 0),
+            (Span::new(5, 9), 1),
+            (Span::new(10, 12), 1),
+            (Span::new(14, 15), 2),
+            (Span::new(15, 16), 2),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn words() {
+        let args = ["-o", "a b", "--", "--x"];
+        let words = args.iter().map(|arg| Ok::<_, TokenizeError>(Word::literal(arg)));
+
+        assert_eq!(tokens(Lexer::from_words(words).value_flags(&["o"])), vec!["-o", "=a b", "|", "--x"]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut lexer = Lexer::new("-a 'b");
+
+        assert!(matches!(lexer.next(), Some(Ok(Token::ShortFlag(_)))));
+        assert_eq!(lexer.next(), Some(Err(TokenizeError::UnterminatedQuote { quote: '\'', offset: 3 })));
+        assert_eq!(lexer.next(), None);
+    }
+}
@@ -9,35 +9,149 @@
 //! // flags = ["a", "b", "c", "d", "long-flag-a", "long-flag-b", "long-flag-c"]
 //!
 //! flags.contains("a") // true
+//!
+//! // flags listed in the second argument take a value
+//! let parsed = flag_parser::parse("build -o out.txt --level=3 -v src", &["o", "level"])?;
+//! // parsed.flags = [Flag { name: "o", value: Some("out.txt") },
+//! //                 Flag { name: "level", value: Some("3") },
+//! //                 Flag { name: "v", value: None }]
+//! // parsed.positionals = ["build", "src"]
+//!
+//! // words are split like a shell would split them
+//! let parsed = flag_parser::parse(r#"--msg "hello world" my\ file"#, &["msg"])?;
+//! // parsed.flags = [Flag { name: "msg", value: Some("hello world") }]
+//! // parsed.positionals = ["my file"]
+//!
+//! // arguments from the OS are already split
+//! let parsed = flag_parser::parse_args(std::env::args_os().skip(1), &["o"]);
 //! ```
+//!
+//! ## Features
+//!
+//! - `std`, on by default: everything. Without it the crate is `no_std`.
+//! - `alloc`, implied by `std`: [`get_flags`] and [`Word::unquote`](tokenizer::Word::unquote).
+//!   The [`tokenizer`] and the [`lexer`] need no allocator at all.
+//! - `derive`: `#[derive(Flags)]`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-use std::collections::HashSet;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+mod flags;
+#[cfg(feature = "std")]
+mod completion;
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "std")]
+mod env;
+#[cfg(feature = "std")]
+mod help;
+pub mod lexer;
+#[cfg(feature = "std")]
+mod manual;
+#[cfg(feature = "std")]
+mod parsed;
+#[cfg(feature = "std")]
+mod parser;
+#[cfg(feature = "std")]
+mod response;
+#[cfg(feature = "std")]
+mod rules;
+mod span;
+#[cfg(feature = "std")]
+mod spec;
+#[cfg(feature = "std")]
+mod suggest;
+pub mod tokenizer;
 
-/// Returns a vector with all flags in a given input
+#[cfg(feature = "std")]
+pub use completion::Shell;
+#[cfg(feature = "std")]
+pub use config::Config;
+#[cfg(feature = "std")]
+pub use env::{Env, SystemEnv};
+#[cfg(feature = "std")]
+pub use error::ParseError;
+#[cfg(feature = "std")]
+pub use flags::Flags;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flags::count as __count;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flags::required as __required;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flags::switch as __switch;
+#[cfg(feature = "derive")]
+pub use flag_parser_derive::Flags;
+pub use lexer::{Lexer, Token};
+#[cfg(feature = "std")]
+pub use parsed::{Flag, Parsed, Positional, Source, Subcommand, Value};
+#[cfg(feature = "std")]
+pub use parser::{parse, parse_args};
+#[cfg(feature = "std")]
+pub(crate) use parser::{parse_args_with, parse_with, Schema, Written};
+#[cfg(feature = "std")]
+pub use response::{expand_response_files, FsLoader, Loader};
+#[cfg(feature = "std")]
+pub use rules::{Group, Violation};
+pub use span::Span;
+#[cfg(feature = "std")]
+pub use spec::{FlagSpec, Spec};
+pub use tokenizer::TokenizeError;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Returns a vector with all flags in a given input, each listed once in
+/// the order it first appears
+///
+/// Words after a bare `--` are never flags, and a bare `-` is not a flag either.
+/// The input is split into words by the [`tokenizer`], so `"a -b"` is a single
+/// word rather than a flag. Reading stops at an unterminated quote.
 ///
-/// It assumes that all characters in the input are
-/// convertable to a single u8 integer like ASCII.
+/// Short flags are split by grapheme cluster, so any valid input is fine,
+/// `-é` included.
+///
+/// Flags are named with their quotes removed, like in [`parse`], and borrowed
+/// from the input. Quotes around a name are fine, `--"long"` gives `long`, but
+/// flags whose name isn't written anywhere in the input without quoting, like
+/// `--a\"b`, are left out; use [`parse`] or the [`Lexer`] for those.
+#[cfg(feature = "alloc")]
 pub fn get_flags(input: &str) -> Vec<&str> {
-    let mut found_flags: HashSet<&str> = HashSet::new();
-    input.split_whitespace()
-        .filter(|word| word.starts_with("-"))
-        .for_each(|word| {
-            if word.starts_with("--") { found_flags.insert(&word[2..]); } else {
-                word[1..].as_bytes()
-                    .iter()
-                    .enumerate()
-                    .for_each(|(i, _)| {
-                        found_flags.insert(&word[i + 1..i + 2]);
-                    })
-            }
-        });
-
-    found_flags.into_iter().collect()
+    let mut found_flags: Vec<&str> = Vec::new();
+    for token in Lexer::new(input).map_while(Result::ok) {
+        if let Token::ShortFlag(name) | Token::LongFlag(name) = token {
+            let name = match name.as_str() {
+                Some(name) => name,
+                None => {
+                    let (raw, unquoted) = (name.raw(), name.unquote());
+                    match raw.find(&*unquoted) {
+                        Some(start) => &raw[start..start + unquoted.len()],
+                        None => continue,
+                    }
+                }
+            };
+            if !found_flags.contains(&name) { found_flags.push(name); }
+        }
+    }
+
+    found_flags
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use std::ffi::{OsStr, OsString};
+
+    fn pairs<'a>(flags: &'a [Flag]) -> Vec<(&'a str, Option<&'a str>)> {
+        flags.iter().map(|flag| (flag.name.as_ref(), flag.value.as_ref().and_then(Value::as_str))).collect()
+    }
 
     #[test]
     fn short_flags() {
@@ -110,4 +224,216 @@ mod tests {
         assert!(flags.contains(&"long-flag-c"));
         assert!(!flags.contains(&"long-flag-d"));
     }
+
+    #[test]
+    fn long_flag_values() {
+        let flags = parse("--output=out.txt --level 3 --verbose", &["output", "level"]).unwrap().flags;
+
+        assert_eq!(pairs(&flags), vec![
+            ("output", Some("out.txt")),
+            ("level", Some("3")),
+            ("verbose", None),
+        ]);
+    }
+
+    #[test]
+    fn short_flag_values() {
+        let flags = parse("-oout.txt -l 3 -vn", &["o", "l"]).unwrap().flags;
+
+        assert_eq!(pairs(&flags), vec![
+            ("o", Some("out.txt")),
+            ("l", Some("3")),
+            ("v", None),
+            ("n", None),
+        ]);
+    }
+
+    #[test]
+    fn short_flag_value_ends_cluster() {
+        let flags = parse("-vofile -o", &["o"]).unwrap().flags;
+
+        assert_eq!(pairs(&flags), vec![
+            ("v", None),
+            ("o", Some("file")),
+            ("o", None),
+        ]);
+    }
+
+    #[test]
+    fn positionals() {
+        let parsed = parse("build src/main.rs -v --out dir extra", &["out"]).unwrap();
+
+        assert_eq!(parsed.positional_values(), vec!["build", "src/main.rs", "extra"]);
+        assert_eq!(pairs(&parsed.flags), vec![
+            ("v", None),
+            ("out", Some("dir")),
+        ]);
+    }
+
+    #[test]
+    fn terminator() {
+        let flags = get_flags("a -b -- -c --d");
+
+        assert_eq!(flags, vec!["b"]);
+
+        let parsed = parse("a -b -- -c --d -- -", &[]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![("b", None)]);
+        assert_eq!(parsed.positional_values(), vec!["a", "-c", "--d", "--", "-"]);
+    }
+
+    #[test]
+    fn quoted_flags() {
+        let input = r#"--"long" -'ab' --'x y' --a\"b -c"#;
+
+        assert_eq!(get_flags(input), vec!["long", "a", "b", "x y", "c"]);
+        assert_eq!(parse(input, &[]).unwrap().names(), vec!["long", "a", "b", "x y", "a\"b", "c"]);
+    }
+
+    #[test]
+    fn nameless_long_flags() {
+        assert_eq!(get_flags("--=x -a --="), vec!["a"]);
+        assert_eq!(parse("--=x", &[]).unwrap().positional_values(), vec!["--=x"]);
+    }
+
+    #[test]
+    fn dash_is_positional() {
+        let flags = get_flags("cat - -n");
+
+        assert_eq!(flags, vec!["n"]);
+
+        let parsed = parse("cat - -n", &[]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![("n", None)]);
+        assert_eq!(parsed.positional_values(), vec!["cat", "-"]);
+    }
+
+    #[test]
+    fn terminator_as_value() {
+        let parsed = parse("-o -- -v", &["o"]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![
+            ("o", Some("--")),
+            ("v", None),
+        ]);
+    }
+
+    #[test]
+    fn quoted_values() {
+        let parsed = parse(r#"--msg "hello world" --path=my\ file -m'a b' "two words" 'x y'"#, &["msg", "path", "m"]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![
+            ("msg", Some("hello world")),
+            ("path", Some("my file")),
+            ("m", Some("a b")),
+        ]);
+        assert_eq!(parsed.positional_values(), vec!["two words", "x y"]);
+    }
+
+    #[test]
+    fn quoted_words_are_not_split() {
+        let flags = get_flags(r#"-a "b -c" d\ -e"#);
+
+        assert_eq!(flags, vec!["a"]);
+    }
+
+    #[test]
+    fn line_continuation() {
+        let parsed = parse("build \\\n  --release \\\n  -j 4", &["j"]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![("release", None), ("j", Some("4"))]);
+        assert_eq!(parsed.positional_values(), vec!["build"]);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let error = parse("--msg 'oops", &["msg"]).unwrap_err();
+
+        assert_eq!(error, ParseError::UnterminatedQuote { quote: '\'', span: Span::new(6, 7) });
+
+        let flags = get_flags("-a --msg 'oops -b");
+
+        assert_eq!(flags.len(), 2);
+        assert!(flags.contains(&"a"));
+        assert!(flags.contains(&"msg"));
+    }
+
+    #[test]
+    fn non_ascii_short_flags() {
+        let flags = get_flags("-é -ßü --größe");
+
+        assert_eq!(flags.len(), 4);
+        assert!(flags.contains(&"é"));
+        assert!(flags.contains(&"ß"));
+        assert!(flags.contains(&"ü"));
+        assert!(flags.contains(&"größe"));
+    }
+
+    #[test]
+    fn grapheme_short_flags() {
+        let parsed = parse("-e\u{301}x -🇧🇷o日本", &["o"]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![
+            ("e\u{301}", None),
+            ("x", None),
+            ("🇧🇷", None),
+            ("o", Some("日本")),
+        ]);
+    }
+
+    #[test]
+    fn grapheme_short_flags_before_quotes() {
+        let parsed = parse("-e\u{301}x'y\u{308}'z\\ü -\"a\u{301}\"", &[]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![
+            ("e\u{301}", None),
+            ("x", None),
+            ("y\u{308}", None),
+            ("z", None),
+            ("ü", None),
+            ("a\u{301}", None),
+        ]);
+    }
+
+    #[test]
+    fn quoted_non_ascii_short_flags() {
+        let parsed = parse("-'éß' \\ü", &[]).unwrap();
+
+        assert_eq!(pairs(&parsed.flags), vec![("é", None), ("ß", None)]);
+        assert_eq!(parsed.positional_values(), vec!["ü"]);
+    }
+
+    #[test]
+    fn args() {
+        let parsed = parse_args(["build", "--msg", "hello world", "-o'x'", "--", "-v"], &["msg", "o"]);
+
+        assert_eq!(pairs(&parsed.flags), vec![("msg", Some("hello world")), ("o", Some("'x'"))]);
+        assert_eq!(parsed.positional_values(), vec!["build", "-v"]);
+        assert_eq!(parsed.positionals[1].index, 5);
+        assert_eq!(parsed.flags[1].value_span, Some(Span::new(2, 5)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_args() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = OsStr::from_bytes(b"caf\xe9.txt");
+        let mut long = OsString::from("--path=");
+        long.push(path);
+        let parsed = parse_args([OsStr::new("-o"), path, &long, path], &["o", "path"]);
+
+        assert_eq!(parsed.flags[0].value, Some(Value::Os(path.to_owned())));
+        assert_eq!(parsed.flags[1].name, "path");
+        assert_eq!(parsed.flags[1].value, Some(Value::Os(path.to_owned())));
+        assert_eq!(parsed.positionals[0].value.as_os_str(), path);
+        assert_eq!(parsed.positionals[0].value.as_str(), None);
+
+        let parsed = parse_args([OsStr::from_bytes(b"--p\xffth=caf\xe9"), OsStr::from_bytes(b"\xff-x")], &[]);
+        assert_eq!(parsed.flags[0].name, "p\u{FFFD}th");
+        assert_eq!(parsed.flags[0].span, Span::new(0, 6));
+        assert_eq!(parsed.flags[0].value, Some(Value::Os(OsStr::from_bytes(b"caf\xe9").to_owned())));
+        assert_eq!(parsed.flags[0].value_span, Some(Span::new(7, 11)));
+        assert_eq!(parsed.positionals[0].span, Span::new(0, 3));
+    }
 }
@@ -0,0 +1,217 @@
+use crate::help::description;
+use crate::{FlagSpec, Spec};
+
+impl Spec {
+    /// A man page for the command in roff, using the man(7) macros
+    ///
+    /// It has NAME, SYNOPSIS, DESCRIPTION, OPTIONS and SUBCOMMANDS sections,
+    /// the last listing every subcommand, however deeply nested, with its flags.
+    ///
+    /// ```Rust
+    /// std::fs::write("mytool.1", spec.man_page())?;
+    /// ```
+    pub fn man_page(&self) -> String {
+        let mut page = format!(".TH {} 1\n", roff(&self.name().to_uppercase()));
+        page += ".SH NAME\n";
+        page += &roff(self.name());
+        if let Some(about) = self.about_text() {
+            page += &format!(" \\- {}", roff(about));
+        }
+        page += "\n.SH SYNOPSIS\n";
+        page += &format!("\\fB{}\\fR {}\n", roff(&self.command_path()), roff(&usage_args(self)));
+        if let Some(about) = self.about_text() {
+            page += &format!(".SH DESCRIPTION\n{}\n", roff(about));
+        }
+        page += ".SH OPTIONS\n";
+        page += &man_options(self);
+
+        let subcommands = nested_subcommands(self);
+        if !subcommands.is_empty() {
+            page += ".SH SUBCOMMANDS\n";
+            for subcommand in subcommands {
+                page += &format!(".SS \"{}\"\n", roff(&subcommand.command_path()));
+                if let Some(about) = subcommand.about_text() {
+                    page += &format!("{}\n.PP\n", roff(about));
+                }
+                page += &format!("\\fB{}\\fR {}\n", roff(&subcommand.command_path()), roff(&usage_args(subcommand)));
+                page += &man_options(subcommand);
+            }
+        }
+        page
+    }
+
+    /// A Markdown reference for the command, with the same sections as
+    /// [`Spec::man_page`]
+    pub fn markdown(&self) -> String {
+        let mut page = format!("# {}\n\n## Name\n\n{}", self.name(), self.name());
+        if let Some(about) = self.about_text() {
+            page += &format!(" - {}", about);
+        }
+        page += &format!("\n\n## Synopsis\n\n```text\n{} {}\n```\n\n", self.command_path(), usage_args(self));
+        if let Some(about) = self.about_text() {
+            page += &format!("## Description\n\n{}\n\n", about);
+        }
+        page += "## Options\n\n";
+        page += &markdown_options(self);
+
+        let subcommands = nested_subcommands(self);
+        if !subcommands.is_empty() {
+            page += "\n## Subcommands\n";
+            for subcommand in subcommands {
+                page += &format!("\n### {}\n\n", subcommand.command_path());
+                if let Some(about) = subcommand.about_text() {
+                    page += &format!("{}\n\n", about);
+                }
+                page += &format!("```text\n{} {}\n```\n\n", subcommand.command_path(), usage_args(subcommand));
+                page += &markdown_options(subcommand);
+            }
+        }
+        page
+    }
+}
+
+/// What follows the command name in a usage line
+fn usage_args(spec: &Spec) -> String {
+    match spec.subcommands().is_empty() {
+        true => "[OPTIONS]".to_string(),
+        false => "[OPTIONS] <COMMAND>".to_string(),
+    }
+}
+
+/// Every subcommand of a spec, depth first
+fn nested_subcommands(spec: &Spec) -> Vec<&Spec> {
+    let mut subcommands = Vec::new();
+    for subcommand in spec.subcommands() {
+        subcommands.push(subcommand);
+        subcommands.extend(nested_subcommands(subcommand));
+    }
+    subcommands
+}
+
+/// Every way a flag can be written, like `-o, --output <FILE>`, with `bold`
+/// around names and `italic` around the value placeholder
+fn synopsis(flag: &FlagSpec, bold: impl Fn(&str) -> String, italic: impl Fn(&str) -> String) -> String {
+    let shorts = flag.shorts().iter().map(|short| bold(&format!("-{}", short)));
+    let longs = flag.longs().iter().chain(flag.aliases()).map(|long| bold(&format!("--{}", long)));
+    let mut names = shorts.chain(longs).collect::<Vec<_>>().join(", ");
+    if flag.needs_value() {
+        names += &format!(" {}", italic(&flag.placeholder()));
+    }
+    names
+}
+
+fn man_options(spec: &Spec) -> String {
+    let mut options = String::new();
+    for flag in spec.flags_with_help() {
+        let bold = |name: &str| format!("\\fB{}\\fR", roff(name));
+        let names = synopsis(&flag, bold, |value| format!("\\fI{}\\fR", roff(value)));
+        options += &format!(".TP\n{}\n", names);
+        let description = description(&flag);
+        if !description.is_empty() {
+            options += &format!("{}\n", roff(&description));
+        }
+    }
+    options
+}
+
+fn markdown_options(spec: &Spec) -> String {
+    let mut options = String::new();
+    for flag in spec.flags_with_help() {
+        let names = synopsis(&flag, |name| format!("`{}`", name), |value| format!("`<{}>`", value));
+        options += &format!("- {}", names);
+        let description = description(&flag);
+        if !description.is_empty() {
+            options += &format!(": {}", description);
+        }
+        options.push('\n');
+    }
+    options
+}
+
+/// Escapes text for roff, so it is printed as it is
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    match escaped.starts_with(['.', '\'']) {
+        true => format!("\\&{}", escaped),
+        false => escaped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .about("Does things to files")
+            .flag(FlagSpec::new("verbose").short('v').long("verbose").help("Print more"))
+            .flag(FlagSpec::new("output").short('o').long("output").takes_value().value_name("FILE").default_value("a"))
+            .subcommand(
+                Spec::new("remote")
+                    .about("Manage remotes")
+                    .subcommand(Spec::new("add").flag(FlagSpec::new("fetch").short('f').help("Fetch after adding"))),
+            )
+    }
+
+    #[test]
+    fn man_page() {
+        assert_eq!(spec().man_page(), r#".TH MYTOOL 1
+.SH NAME
+mytool \- Does things to files
+.SH SYNOPSIS
+\fBmytool\fR [OPTIONS] <COMMAND>
+.SH DESCRIPTION
+Does things to files
+.SH OPTIONS
+.TP
+\fB\-v\fR, \fB\-\-verbose\fR
+Print more
+.TP
+\fB\-o\fR, \fB\-\-output\fR \fIFILE\fR
+[default: a]
+.TP
+\fB\-h\fR, \fB\-\-help\fR
+Print help
+.SH SUBCOMMANDS
+.SS "mytool remote"
+Manage remotes
+.PP
+\fBmytool remote\fR [OPTIONS] <COMMAND>
+.TP
+\fB\-h\fR, \fB\-\-help\fR
+Print help
+.SS "mytool remote add"
+\fBmytool remote add\fR [OPTIONS]
+.TP
+\fB\-f\fR
+Fetch after adding
+.TP
+\fB\-h\fR, \fB\-\-help\fR
+Print help
+"#);
+    }
+
+    #[test]
+    fn markdown() {
+        let page = spec().markdown();
+
+        assert!(page.starts_with(
+            "# mytool\n\n## Name\n\nmytool - Does things to files\n\n\
+             ## Synopsis\n\n```text\nmytool [OPTIONS] <COMMAND>\n```\n\n\
+             ## Description\n\nDoes things to files\n\n## Options\n\n"
+        ));
+        assert!(Spec::new("ls").markdown().starts_with("# ls\n\n## Name\n\nls\n\n## Synopsis\n\n"));
+        assert!(page.contains("- `-o`, `--output` `<FILE>`: [default: a]\n"));
+        assert!(page.contains("\n## Subcommands\n\n### mytool remote\n\nManage remotes\n\n"));
+        assert!(page.ends_with(
+            "### mytool remote add\n\n```text\nmytool remote add [OPTIONS]\n```\n\n\
+             - `-f`: Fetch after adding\n- `-h`, `--help`: Print help\n"
+        ));
+    }
+
+    #[test]
+    fn roff_escapes() {
+        assert_eq!(roff(r"a-b\c"), r"a\-b\ec");
+        assert_eq!(roff(".hidden"), r"\&.hidden");
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{ParseError, Span};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::str::FromStr;
+
+/// The value of a flag or a positional argument
+///
+/// Values are text, except for arguments given by the OS that aren't valid
+/// UTF-8, which are kept as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value<'a> {
+    Str(Cow<'a, str>),
+    Os(OsString),
+}
+
+impl<'a> Value<'a> {
+    /// The value as text, if it is valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(text) => Some(text),
+            Value::Os(os) => os.to_str(),
+        }
+    }
+
+    /// The value as text, with invalid UTF-8 replaced by `U+FFFD`
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        match self {
+            Value::Str(text) => Cow::Borrowed(text),
+            Value::Os(os) => os.to_string_lossy(),
+        }
+    }
+
+    pub fn as_os_str(&self) -> &OsStr {
+        match self {
+            Value::Str(text) => OsStr::new(text.as_ref()),
+            Value::Os(os) => os,
+        }
+    }
+
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Str(text) => Value::Str(Cow::Owned(text.into_owned())),
+            Value::Os(os) => Value::Os(os),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(text: &'a str) -> Value<'a> {
+        Value::Str(Cow::Borrowed(text))
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Value<'a> {
+    fn from(text: Cow<'a, str>) -> Value<'a> {
+        Value::Str(text)
+    }
+}
+
+impl PartialEq<str> for Value<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for Value<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+/// A flag found in the input together with its value, if it has one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flag<'a> {
+    pub name: Cow<'a, str>,
+    /// How messages write the flag: as it was written in the input, like
+    /// `--port` or `-p`, or else the environment variable it came from or the
+    /// flag as help writes it
+    pub written: String,
+    pub value: Option<Value<'a>>,
+    /// Index of the word the flag was found in
+    pub index: usize,
+    /// Where the flag is in the input: `--name` for long flags, and the
+    /// single flag inside its cluster for short flags
+    pub span: Span,
+    /// Where the value is in the input, quotes included
+    pub value_span: Option<Span>,
+    /// Where the flag came from. Flags that don't come from the input have an
+    /// index of 0 and empty spans.
+    pub source: Source,
+    /// Whether the flag was switched off, by `--no-name` or a false value like
+    /// `--name=false`. Switches given a value that way keep no value.
+    pub negated: bool,
+}
+
+/// Where a flag came from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Source {
+    /// The input that was parsed
+    #[default]
+    CommandLine,
+    /// The environment variable with this name
+    Env(String),
+    /// The config with this name, at this line
+    Config { name: String, line: usize },
+    /// The default value of the flag
+    Default,
+}
+
+/// A positional argument found in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Positional<'a> {
+    pub value: Value<'a>,
+    /// Index of the word the argument was found in
+    pub index: usize,
+    /// Where the argument is in the input, quotes included
+    pub span: Span,
+}
+
+/// Everything found in an input: its flags and its positional arguments,
+/// each kept in the order they were given
+///
+/// Every occurrence of a flag is kept, so `-vvv` holds three `v` flags and
+/// `--include a --include b` holds both values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Parsed<'a> {
+    pub flags: Vec<Flag<'a>>,
+    pub positionals: Vec<Positional<'a>>,
+    /// Flags from outside the input, such as environment variables and
+    /// defaults, used for flags the input doesn't give
+    pub fallbacks: Vec<Flag<'a>>,
+    /// The subcommand selected, with everything given after it
+    pub subcommand: Option<Box<Subcommand<'a>>>,
+}
+
+/// A subcommand selected in the input, such as `remote` in `git remote add`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subcommand<'a> {
+    /// The name the subcommand is declared with
+    pub name: String,
+    /// Index of the word the subcommand was found in
+    pub index: usize,
+    /// Where the subcommand is in the input
+    pub span: Span,
+    /// The flags, positionals and subcommand given to the subcommand
+    pub parsed: Parsed<'a>,
+}
+
+impl Flag<'_> {
+    pub fn into_owned(self) -> Flag<'static> {
+        Flag {
+            name: Cow::Owned(self.name.into_owned()),
+            value: self.value.map(Value::into_owned),
+            ..self
+        }
+    }
+}
+
+impl Flag<'_> {
+    /// The value of the flag converted with [`FromStr`], `None` when it has no value
+    ///
+    /// Conversion failures are [`ParseError::InvalidValue`] errors naming the flag.
+    pub fn parse_value<T>(&self) -> Result<Option<T>, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let Some(value) = &self.value else { return Ok(None) };
+        let invalid = |reason: String| ParseError::InvalidValue {
+            flag: self.written.clone(),
+            value: value.to_string(),
+            reason,
+            span: self.value_span.unwrap_or(self.span),
+        };
+
+        match value.as_str() {
+            Some(text) => text.parse().map(Some).map_err(|error: T::Err| invalid(error.to_string())),
+            None => Err(invalid("not valid UTF-8".to_string())),
+        }
+    }
+}
+
+impl Positional<'_> {
+    pub fn into_owned(self) -> Positional<'static> {
+        Positional { value: self.value.into_owned(), ..self }
+    }
+}
+
+impl<'a> Parsed<'a> {
+    /// The result with everything borrowed from the input copied
+    pub fn into_owned(self) -> Parsed<'static> {
+        Parsed {
+            flags: self.flags.into_iter().map(Flag::into_owned).collect(),
+            positionals: self.positionals.into_iter().map(Positional::into_owned).collect(),
+            fallbacks: self.fallbacks.into_iter().map(Flag::into_owned).collect(),
+            subcommand: self.subcommand.map(|subcommand| {
+                Box::new(Subcommand { parsed: subcommand.parsed.into_owned(), ..*subcommand })
+            }),
+        }
+    }
+
+    /// The names of the subcommands selected, outermost first, so
+    /// `["remote", "add"]` for `git remote add`
+    pub fn subcommand_path(&self) -> Vec<&str> {
+        let mut path = Vec::new();
+        let mut parsed = self;
+        while let Some(subcommand) = &parsed.subcommand {
+            path.push(subcommand.name.as_str());
+            parsed = &subcommand.parsed;
+        }
+        path
+    }
+
+    /// What was given to the innermost subcommand selected, or to the command
+    /// itself when there is none
+    pub fn innermost(&self) -> &Parsed<'a> {
+        match &self.subcommand {
+            Some(subcommand) => subcommand.parsed.innermost(),
+            None => self,
+        }
+    }
+
+    /// Whether the flag was given and not switched off by its last occurrence
+    pub fn contains(&self, name: &str) -> bool {
+        self.switch(name) == Some(true)
+    }
+
+    /// Whether the last occurrence of the flag switches it on or off, `None`
+    /// when it isn't given
+    ///
+    /// ```Rust
+    /// // --color --no-color --color=yes
+    /// parsed.switch("color") // Some(true)
+    /// ```
+    pub fn switch(&self, name: &str) -> Option<bool> {
+        self.occurrences(name).last().map(|flag| !flag.negated)
+    }
+
+    /// How many times the flag was given since it was last switched off
+    pub fn count(&self, name: &str) -> usize {
+        self.occurrences(name).fold(0, |count, flag| if flag.negated { 0 } else { count + 1 })
+    }
+
+    /// Every occurrence of the flag, in input order
+    ///
+    /// A flag the input doesn't give falls back to its entry in
+    /// [`Parsed::fallbacks`], if it has one.
+    pub fn occurrences<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s Flag<'a>> + 's {
+        self.layer(name).iter().filter(move |flag| flag.name == name)
+    }
+
+    /// Where the last value of the flag came from, or where the flag came from
+    /// when it has no value
+    pub fn source(&self, name: &str) -> Option<&Source> {
+        let mut flags = self.layer(name).iter().rev().filter(|flag| flag.name == name);
+        let flag = flags.clone().find(|flag| flag.value.is_some()).or_else(|| flags.next())?;
+        Some(&flag.source)
+    }
+
+    /// The values given to the flag, in input order
+    ///
+    /// Values that aren't valid UTF-8 are skipped, see [`Flag::value`] for those.
+    pub fn values_of<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s str> + 's {
+        self.occurrences(name).filter_map(|flag| flag.value.as_ref()?.as_str())
+    }
+
+    /// The last value given to the flag
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        let mut flags = self.layer(name).iter().rev().filter(|flag| flag.name == name);
+        flags.find_map(|flag| flag.value.as_ref()?.as_str())
+    }
+
+    /// The last value given to the flag, converted with [`FromStr`]
+    ///
+    /// ```Rust
+    /// let port: Option<u16> = parsed.get("port")?;
+    /// ```
+    ///
+    /// Conversion failures are [`ParseError::InvalidValue`] errors with the name
+    /// of the flag, the value and its span.
+    pub fn get<T>(&self, name: &str) -> Result<Option<T>, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.occurrences(name).filter(|flag| flag.value.is_some()).last() {
+            Some(flag) => flag.parse_value(),
+            None => Ok(None),
+        }
+    }
+
+    /// Every value given to the flag, in input order, converted with [`FromStr`]
+    pub fn get_many<T>(&self, name: &str) -> Result<Vec<T>, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.occurrences(name).filter_map(|flag| flag.parse_value().transpose()).collect()
+    }
+
+    /// The values of all positional arguments, in input order, with invalid
+    /// UTF-8 replaced by `U+FFFD`
+    pub fn positional_values(&self) -> Vec<Cow<'_, str>> {
+        self.positionals.iter().map(|positional| positional.value.to_string_lossy()).collect()
+    }
+
+    /// The names of all flags, each listed once in the order it first appears
+    pub fn names(&self) -> Vec<&str> {
+        self.counts().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// The names of all flags with how many times each was given, in the
+    /// order they first appear, followed by the fallbacks of flags not given
+    pub fn counts(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for flag in &self.flags {
+            match counts.iter_mut().find(|(name, _)| *name == flag.name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&flag.name, 1)),
+            }
+        }
+        for flag in &self.fallbacks {
+            if !counts.iter().any(|(name, _)| *name == flag.name) {
+                counts.push((&flag.name, 1));
+            }
+        }
+        counts
+    }
+
+    /// The flags to look the flag up in: the ones from the input when it gives
+    /// the flag, and the fallbacks otherwise
+    fn layer(&self, name: &str) -> &[Flag<'a>] {
+        match self.flags.iter().any(|flag| flag.name == name) {
+            true => &self.flags,
+            false => &self.fallbacks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, ParseError, Span};
+
+    #[test]
+    fn counts() {
+        let parsed = parse("-vvv --include a -x --include b -v", &["include"]).unwrap();
+
+        assert_eq!(parsed.count("v"), 4);
+        assert_eq!(parsed.count("include"), 2);
+        assert_eq!(parsed.count("y"), 0);
+        assert_eq!(parsed.counts(), vec![("v", 4), ("include", 2), ("x", 1)]);
+        assert_eq!(parsed.names(), vec!["v", "include", "x"]);
+    }
+
+    #[test]
+    fn values() {
+        let parsed = parse("--include a -x --include b", &["include"]).unwrap();
+
+        assert_eq!(parsed.values_of("include").collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(parsed.value_of("include"), Some("b"));
+        assert_eq!(parsed.value_of("x"), None);
+        assert!(parsed.contains("x"));
+        assert!(!parsed.contains("y"));
+    }
+
+    #[test]
+    fn typed_values() {
+        let parsed = parse("--port 80 --port=8080 -n 1 -n 2 -n 3", &["port", "n"]).unwrap();
+
+        assert_eq!(parsed.get::<u16>("port"), Ok(Some(8080)));
+        assert_eq!(parsed.get::<u16>("host"), Ok(None));
+        assert_eq!(parsed.get_many::<u8>("n"), Ok(vec![1, 2, 3]));
+        assert_eq!(parsed.get_many::<u8>("host"), Ok(vec![]));
+    }
+
+    #[test]
+    fn invalid_typed_values() {
+        let parsed = parse("--port 80 --port=http -n 1 -n 300", &["port", "n"]).unwrap();
+        let error = parsed.get::<u16>("port").unwrap_err();
+
+        assert_eq!(error, ParseError::InvalidValue {
+            flag: "--port".to_string(),
+            value: "http".to_string(),
+            reason: "invalid digit found in string".to_string(),
+            span: Span::new(17, 21),
+        });
+        assert_eq!(error.to_string(), "invalid value `http` for `--port`: invalid digit found in string");
+        assert!(matches!(
+            parsed.get_many::<u8>("n"),
+            Err(ParseError::InvalidValue { value, .. }) if value == "300"
+        ));
+    }
+
+    #[test]
+    fn indices() {
+        let parsed = parse("build -vv --out dir -x", &["out"]).unwrap();
+        let indices: Vec<_> = parsed.flags.iter().map(|flag| (flag.name.as_ref(), flag.index)).collect();
+
+        assert_eq!(indices, vec![("v", 1), ("v", 1), ("out", 2), ("x", 4)]);
+    }
+
+    #[test]
+    fn spans() {
+        let input = r#"build -vo out --msg="a b" -- --x"#;
+        let parsed = parse(input, &["o", "msg"]).unwrap();
+        let spans: Vec<_> = parsed.flags.iter()
+            .map(|flag| (&input[flag.span.range()], flag.value_span.map(|span| &input[span.range()])))
+            .collect();
+
+        assert_eq!(spans, vec![("v", None), ("o", Some("out")), ("--msg", Some(r#""a b""#))]);
+
+        let spans: Vec<_> = parsed.positionals.iter()
+            .map(|positional| (&input[positional.span.range()], positional.index))
+            .collect();
+
+        assert_eq!(spans, vec![("build", 0), ("--x", 5)]);
+    }
+}
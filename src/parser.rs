@@ -0,0 +1,273 @@
+use crate::lexer::{Lexer, Token};
+use crate::tokenizer::{Tokenizer, Word};
+use crate::{Flag, ParseError, Parsed, Positional, Source, Span, Subcommand, Value};
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::ffi::{OsStr, OsString};
+
+/// Returns all flags and positional arguments in a given input
+///
+/// `value_flags` lists the flags that take a value. Those accept all of
+/// `--name=value`, `--name value`, `-ovalue` and `-o value`. Any other
+/// flag only gets a value when it is written as `--name=value`.
+///
+/// A bare `--` ends the flags: it is dropped and every word after it is a
+/// positional. A bare `-` is a positional too, usually meaning stdin/stdout.
+///
+/// The input is split into words by the [`tokenizer`](crate::tokenizer), so quotes and escapes
+/// work like they do in a shell: `--msg "hello world"` gives `msg` the value
+/// `hello world`.
+///
+/// Like in [`get_flags`](crate::get_flags), each grapheme cluster of a short flag cluster is a flag.
+/// Unlike it, every occurrence is kept, so `-vvv` gives three `v` flags.
+pub fn parse<'a>(input: &'a str, value_flags: &[&str]) -> Result<Parsed<'a>, ParseError> {
+    parse_with(input, &ValueFlags(value_flags))
+}
+
+/// Returns all flags and positional arguments in arguments that are already
+/// split, such as the ones from [`std::env::args_os`]
+///
+/// Flags are found like in [`parse`], but arguments are taken as they are, with
+/// no quoting to remove. Leave out the program name, usually the first argument.
+///
+/// Arguments that aren't valid UTF-8 are kept as [`Value::Os`] when they are a
+/// value or a positional, including the value in `--name=value`, while flag names
+/// get `U+FFFD` for invalid bytes. The index of every flag and positional is the
+/// index of its argument, and its span is a byte range in that argument.
+pub fn parse_args<I>(args: I, value_flags: &[&str]) -> Parsed<'static>
+where
+    I: IntoIterator,
+    I::Item: Into<OsString>,
+{
+    match parse_args_with(args, &ValueFlags(value_flags)) {
+        Ok(parsed) => parsed,
+        Err(error) => match error {},
+    }
+}
+
+/// Decides what the flags found in an input are called and whether they take a value
+pub(crate) trait Schema {
+    type Error;
+
+    /// The name to report for a flag found in the input, and whether it takes a value
+    fn resolve<'a>(&self, flag: Written<'_, 'a>, span: Span) -> Result<(Cow<'a, str>, bool), Self::Error>;
+
+    /// Checks a flag once its value, if any, is known, and settles what it means
+    fn check(&self, _flag: &mut Flag, _written: Written, _takes_value: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The name and schema of the subcommand `word` selects, when it is the
+    /// first positional
+    fn subcommand(&self, _word: &Word, _span: Span) -> Result<Option<(String, &Self)>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// A flag as written in the input, such as `--name` or `-n`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Written<'w, 'a> {
+    /// The name, without its dashes
+    pub(crate) name: &'w Word<'a>,
+    pub(crate) long: bool,
+}
+
+impl std::fmt::Display for Written<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", if self.long { "--" } else { "-" }, self.name.unquote())
+    }
+}
+
+/// The schema of [`parse`]: flags are named as written, and take a value when listed
+struct ValueFlags<'s>(&'s [&'s str]);
+
+impl Schema for ValueFlags<'_> {
+    type Error = Infallible;
+
+    fn resolve<'a>(&self, flag: Written<'_, 'a>, _: Span) -> Result<(Cow<'a, str>, bool), Infallible> {
+        Ok((flag.name.unquote(), self.0.iter().any(|name| flag.name.is(name))))
+    }
+}
+
+pub(crate) fn parse_with<'a, S>(input: &'a str, schema: &S) -> Result<Parsed<'a>, ParseError>
+where
+    S: Schema,
+    ParseError: From<S::Error>,
+{
+    let mut lexer = Lexer::from_words(Tokenizer::new(input).map(|word| -> Result<Word, ParseError> { Ok(word?) }));
+    collect(&mut lexer, schema, &|_, word| Value::Str(word.unquote()), &|_, span| span)
+}
+
+pub(crate) fn parse_args_with<I, S>(args: I, schema: &S) -> Result<Parsed<'static>, S::Error>
+where
+    I: IntoIterator,
+    I::Item: Into<OsString>,
+    S: Schema,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let texts: Vec<Cow<str>> = args.iter().map(|arg| String::from_utf8_lossy(arg.as_encoded_bytes())).collect();
+    let mut lexer = Lexer::from_words(texts.iter().map(|text| Ok(Word::literal(text))));
+    let to_span = |index: usize, span: Span| {
+        let bytes = args[index].as_encoded_bytes();
+        Span::new(raw_offset(bytes, span.start), raw_offset(bytes, span.end))
+    };
+    let to_value = |index: usize, word: Word| {
+        let bytes = args[index].as_encoded_bytes();
+        let start = raw_offset(bytes, word.span().start);
+        match (std::str::from_utf8(&bytes[start..]), os_suffix(bytes, start)) {
+            (Err(_), Some(value)) => Value::Os(value.to_owned()),
+            _ => Value::Str(Cow::Owned(word.unquote().into_owned())),
+        }
+    };
+
+    collect(&mut lexer, schema, &to_value, &to_span).map(Parsed::into_owned)
+}
+
+/// Maps a byte offset in the lossy text of an argument, where every invalid
+/// sequence became one `U+FFFD`, to the same place in the bytes of the argument
+fn raw_offset(bytes: &[u8], offset: usize) -> usize {
+    let (mut raw, mut lossy) = (0, 0);
+    for chunk in bytes.utf8_chunks() {
+        let valid = chunk.valid().len();
+        if offset <= lossy + valid {
+            return raw + offset - lossy;
+        }
+        raw += valid + chunk.invalid().len();
+        lossy += valid + char::REPLACEMENT_CHARACTER.len_utf8();
+    }
+    raw
+}
+
+/// The bytes of an argument from `start` on, unless `start` falls between two
+/// invalid sequences, where encoded bytes can't be split
+fn os_suffix(bytes: &[u8], start: usize) -> Option<&OsStr> {
+    let (before, after) = bytes.split_at(start);
+    let ends_valid = before.utf8_chunks().last().is_none_or(|chunk| chunk.invalid().is_empty());
+    let starts_valid = after.utf8_chunks().next().is_some_and(|chunk| !chunk.valid().is_empty());
+    // SAFETY: the bytes are split right after valid UTF-8 or right before it.
+    (ends_valid || starts_valid).then(|| unsafe { OsStr::from_encoded_bytes_unchecked(after) })
+}
+
+/// Builds a [`Parsed`] from the tokens of a lexer, turning values into
+/// [`Value`]s with `to_value` and word spans into reported spans with `to_span`
+///
+/// When a positional selects a subcommand, the words after it are collected
+/// against the schema of the subcommand.
+fn collect<'a, S, E, W>(
+    lexer: &mut Lexer<'a, W>,
+    schema: &S,
+    to_value: &impl Fn(usize, Word<'a>) -> Value<'a>,
+    to_span: &impl Fn(usize, Span) -> Span,
+) -> Result<Parsed<'a>, E>
+where
+    S: Schema,
+    E: From<S::Error>,
+    W: Iterator<Item = Result<Word<'a>, E>>,
+{
+    let mut flags = Vec::new();
+    let mut positionals = Vec::new();
+    let mut selected = None;
+    scan(
+        lexer,
+        to_span,
+        |written, span| Ok(schema.resolve(written, span)?),
+        |found| {
+            let written = Written { name: &found.name, long: found.long };
+            let mut flag = Flag {
+                name: found.resolved,
+                written: written.to_string(),
+                value: found.value.map(|(index, value)| to_value(index, value)),
+                index: found.index,
+                span: found.span,
+                value_span: found.value.map(|(index, value)| to_span(index, value.span())),
+                source: Source::CommandLine,
+                negated: false,
+            };
+            schema.check(&mut flag, written, found.takes_value)?;
+            flags.push(flag);
+            Ok(())
+        },
+        |index, word, terminated| {
+            let span = to_span(index, word.span());
+            if !terminated && positionals.is_empty() {
+                if let Some(subcommand) = schema.subcommand(&word, span)? {
+                    selected = Some((index, span, subcommand));
+                    return Ok(true);
+                }
+            }
+            positionals.push(Positional { value: to_value(index, word), index, span });
+            Ok(false)
+        },
+    )?;
+
+    let subcommand = match selected {
+        Some((index, span, (name, schema))) => {
+            Some(Box::new(Subcommand { name, index, span, parsed: collect(lexer, schema, to_value, to_span)? }))
+        }
+        None => None,
+    };
+    Ok(Parsed { flags, positionals, fallbacks: Vec::new(), subcommand })
+}
+
+/// A flag found by [`scan`]
+struct Found<'a, N> {
+    /// Index of the word the flag was found in
+    index: usize,
+    /// Where the flag is, covering the dashes of long flags
+    span: Span,
+    /// The flag as written, without its dashes
+    name: Word<'a>,
+    long: bool,
+    /// What the flag resolved to
+    resolved: N,
+    takes_value: bool,
+    /// The value with the index of the word it was found in
+    value: Option<(usize, Word<'a>)>,
+}
+
+/// Walks the tokens of an input, reporting every flag with its value and
+/// every positional with the index of its word
+///
+/// Every flag is first given to `resolve`, which tells whether it takes a value,
+/// with its span turned into the reported one by `to_span`.
+/// Positionals are reported with whether they follow a `--`, and walking stops
+/// early when `on_positional` returns `true`, leaving the rest of the words alone.
+fn scan<'a, N, E, W>(
+    lexer: &mut Lexer<'a, W>,
+    to_span: impl Fn(usize, Span) -> Span,
+    resolve: impl Fn(Written<'_, 'a>, Span) -> Result<(N, bool), E>,
+    mut on_flag: impl FnMut(Found<'a, N>) -> Result<(), E>,
+    mut on_positional: impl FnMut(usize, Word<'a>, bool) -> Result<bool, E>,
+) -> Result<(), E>
+where
+    W: Iterator<Item = Result<Word<'a>, E>>,
+{
+    let mut terminated = false;
+    while let Some(token) = lexer.next().transpose()? {
+        let index = lexer.index();
+        let (name, long) = match token {
+            Token::ShortFlag(name) => (name, false),
+            Token::LongFlag(name) => (name, true),
+            Token::Positional(word) if on_positional(index, word, terminated)? => break,
+            Token::Terminator(_) => {
+                terminated = true;
+                continue;
+            }
+            Token::Positional(_) | Token::Value(_) => continue,
+        };
+        let span = match lexer.current_word() {
+            Some(word) if long => Span::new(word.span().start, name.span().end),
+            _ => name.span(),
+        };
+        let span = to_span(index, span);
+        let (resolved, takes_value) = resolve(Written { name: &name, long }, span)?;
+        let value = match takes_value {
+            true => lexer.value().transpose()?,
+            false => lexer.inline_value(),
+        };
+        let value = value.map(|value| (lexer.index(), value));
+        on_flag(Found { index, span, name, long, resolved, takes_value, value })?;
+    }
+
+    Ok(())
+}
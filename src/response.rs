@@ -0,0 +1,189 @@
+use crate::tokenizer::Tokenizer;
+use crate::{ParseError, Span};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where response files are read from
+///
+/// [`FsLoader`] reads files from disk. Tests can use a `HashMap` of paths to
+/// contents or a closure instead:
+///
+/// ```Rust
+/// let files = HashMap::from([("args.txt".to_string(), "-o out.txt".to_string())]);
+/// let args = expand_response_files(["@args.txt", "-v"], &files)?;
+/// ```
+pub trait Loader {
+    /// The contents of the file at `path`
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// The file system, read with [`std::fs::read_to_string`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsLoader;
+
+impl Loader for FsLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+impl Loader for HashMap<String, String> {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        path.to_str().and_then(|path| self.get(path)).cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+impl<F> Loader for F
+where
+    F: Fn(&Path) -> io::Result<String>,
+{
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self(path)
+    }
+}
+
+/// Replaces every `@path` argument with the arguments in the file at `path`,
+/// the way gcc, javac and MSVC read response files
+///
+/// Files are split into words like the [`tokenizer`](crate::tokenizer) splits
+/// an input, so quotes and escapes work, and any whitespace, newlines included,
+/// separates arguments. Files may include other files, with paths handed to
+/// `loader` as written; a file that ends up including itself is an error.
+///
+/// An argument starting with `@@` stands for itself without the first `@`, in
+/// files too, and a bare `@` is kept as it is.
+///
+/// ```Rust
+/// let args = flag_parser::expand_response_files(std::env::args_os().skip(1), &FsLoader)?;
+/// let parsed = spec.parse_args(args)?;
+/// ```
+pub fn expand_response_files<I>(args: I, loader: &impl Loader) -> Result<Vec<OsString>, ParseError>
+where
+    I: IntoIterator,
+    I::Item: Into<OsString>,
+{
+    let mut expanded = Vec::new();
+    for arg in args {
+        expand(arg.into(), loader, &mut Vec::new(), &mut expanded)?;
+    }
+    Ok(expanded)
+}
+
+/// Expands one argument into `expanded`, with `including` the files it was
+/// found in, outermost first
+fn expand(
+    arg: OsString,
+    loader: &impl Loader,
+    including: &mut Vec<PathBuf>,
+    expanded: &mut Vec<OsString>,
+) -> Result<(), ParseError> {
+    let bytes = arg.as_encoded_bytes();
+    let path = match bytes {
+        [b'@', _, ..] => {
+            // SAFETY: `@` is ASCII, so the bytes are split right after valid UTF-8.
+            unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[1..]) }
+        }
+        _ => {
+            expanded.push(arg);
+            return Ok(());
+        }
+    };
+    if bytes.starts_with(b"@@") {
+        expanded.push(path.to_owned());
+        return Ok(());
+    }
+
+    let path = PathBuf::from(path);
+    let name = path.display().to_string();
+    let invalid = |reason: String| ParseError::InvalidResponseFile { path: name.clone(), reason, span: Span::default() };
+    if including.contains(&path) {
+        let chain: Vec<String> = including.iter().chain([&path]).map(|path| path.display().to_string()).collect();
+        return Err(invalid(format!("includes itself through {}", chain.join(" -> "))));
+    }
+    let text = loader.load(&path).map_err(|error| invalid(error.to_string()))?;
+
+    including.push(path);
+    for word in Tokenizer::new(&text) {
+        let word = word.map_err(|error| invalid(error.to_string()))?;
+        expand(word.unquote().into_owned().into(), loader, including, expanded)?;
+    }
+    including.pop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FlagSpec, Spec};
+
+    fn files(files: &[(&str, &str)]) -> HashMap<String, String> {
+        files.iter().map(|(path, text)| (path.to_string(), text.to_string())).collect()
+    }
+
+    fn expanded(args: &[&str], loader: &impl Loader) -> Result<Vec<String>, ParseError> {
+        let args = expand_response_files(args, loader)?;
+        Ok(args.into_iter().map(|arg| arg.into_string().unwrap()).collect())
+    }
+
+    #[test]
+    fn expands() {
+        let files = files(&[
+            ("args.txt", "-o 'out dir'\n  --level=3 @more.txt\n@more.txt"),
+            ("more.txt", r#"-v "a b""#),
+            ("empty.txt", ""),
+        ]);
+
+        assert_eq!(expanded(&["build", "@args.txt", "@empty.txt", "x"], &files).unwrap(), vec![
+            "build", "-o", "out dir", "--level=3", "-v", "a b", "-v", "a b", "x"
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        let files = files(&[("args.txt", "@@user @")]);
+
+        assert_eq!(expanded(&["@@args.txt", "@args.txt", "@", "a@b"], &files).unwrap(), vec![
+            "@args.txt", "@user", "@", "@", "a@b"
+        ]);
+    }
+
+    #[test]
+    fn cycles() {
+        let files = files(&[("a.txt", "-x @b.txt"), ("b.txt", "@a.txt"), ("self.txt", "@self.txt")]);
+
+        assert_eq!(expanded(&["@a.txt"], &files).unwrap_err(), ParseError::InvalidResponseFile {
+            path: "a.txt".to_string(),
+            reason: "includes itself through a.txt -> b.txt -> a.txt".to_string(),
+            span: Span::default(),
+        });
+        assert!(expanded(&["@self.txt"], &files).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let files = files(&[("quote.txt", "-a 'b")]);
+
+        assert_eq!(
+            expanded(&["@missing.txt"], &files).unwrap_err().to_string(),
+            "response file `missing.txt`: entity not found"
+        );
+        assert_eq!(
+            expanded(&["@quote.txt"], &files).unwrap_err().to_string(),
+            "response file `quote.txt`: unterminated ' quote starting at byte 3"
+        );
+        let denied = |_: &Path| Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert_eq!(expanded(&["@x"], &denied).unwrap_err().to_string(), "response file `x`: denied");
+    }
+
+    #[test]
+    fn parsing() {
+        let spec = Spec::new("cc").flag(FlagSpec::new("output").short('o').takes_value());
+        let args = expand_response_files(["@args.txt", "main.c"], &files(&[("args.txt", "-o a.out")])).unwrap();
+        let parsed = spec.parse_args(args).unwrap();
+
+        assert_eq!(parsed.value_of("output"), Some("a.out"));
+        assert_eq!(parsed.positional_values(), vec!["main.c"]);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{FlagSpec, ParseError, Parsed, Span, Spec};
+use std::fmt;
+
+/// Flags of which one or more must be given
+///
+/// ```Rust
+/// let spec = Spec::new("export")
+///     .flag(FlagSpec::new("json").long("json"))
+///     .flag(FlagSpec::new("yaml").long("yaml"))
+///     .group(Group::exactly_one("format", ["json", "yaml"]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    name: String,
+    members: Vec<String>,
+    exclusive: bool,
+}
+
+impl Group {
+    /// A group of which exactly one flag must be given
+    pub fn exactly_one<I>(name: impl Into<String>, members: I) -> Group
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Group { name: name.into(), members: members.into_iter().map(Into::into).collect(), exclusive: true }
+    }
+
+    /// A group of which at least one flag must be given
+    pub fn at_least_one<I>(name: impl Into<String>, members: I) -> Group
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Group { exclusive: false, ..Group::exactly_one(name, members) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The canonical names of the flags in the group
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    /// Whether giving more than one flag of the group is an error
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+/// A rule of a spec that parse results break, see [`ParseError::Violations`]
+///
+/// Flags are written the way messages write them, like `--json`, and spans
+/// point at the last occurrence of the offending flag, or are empty when it
+/// didn't come from the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// A required flag wasn't given
+    Missing { flag: String },
+    /// A flag was given together with one it conflicts with
+    Conflict { flag: String, other: String, span: Span },
+    /// A flag was given without one it requires
+    Unmet { flag: String, requires: String, span: Span },
+    /// No flag of a group was given
+    NoneOfGroup { group: String, members: Vec<String> },
+    /// More than one flag of a group that takes exactly one was given
+    ManyOfGroup { group: String, given: Vec<String>, span: Span },
+}
+
+impl Violation {
+    pub fn span(&self) -> Span {
+        match self {
+            Violation::Conflict { span, .. } | Violation::Unmet { span, .. } | Violation::ManyOfGroup { span, .. } => {
+                *span
+            }
+            Violation::Missing { .. } | Violation::NoneOfGroup { .. } => Span::default(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Missing { flag } => write!(f, "missing required flag `{}`", flag),
+            Violation::Conflict { flag, other, .. } => write!(f, "`{}` can't be given with `{}`", flag, other),
+            Violation::Unmet { flag, requires, .. } => write!(f, "`{}` requires `{}`", flag, requires),
+            Violation::NoneOfGroup { members, .. } => write!(f, "one of {} must be given", quoted(members)),
+            Violation::ManyOfGroup { given, .. } => write!(f, "only one of {} can be given", quoted(given)),
+        }
+    }
+}
+
+fn quoted(flags: &[String]) -> String {
+    flags.iter().map(|flag| format!("`{}`", flag)).collect::<Vec<_>>().join(", ")
+}
+
+impl Spec {
+    /// Checks parse results against the required flags, conflicts,
+    /// requirements and groups of the spec and of the subcommand selected
+    ///
+    /// Every rule broken is reported, in one [`ParseError::Violations`]. Flags
+    /// switched off count as not given, and required flags with a default as
+    /// given.
+    pub fn validate(&self, parsed: &Parsed) -> Result<(), ParseError> {
+        let mut violations = Vec::new();
+        self.violations(parsed, &mut violations);
+        match violations.first() {
+            Some(first) => Err(ParseError::Violations { span: first.span(), violations }),
+            None => Ok(()),
+        }
+    }
+
+    fn violations(&self, parsed: &Parsed, violations: &mut Vec<Violation>) {
+        let given = |name: &str| match parsed.contains(name) {
+            true => parsed.occurrences(name).last().map(|flag| flag.span),
+            false => None,
+        };
+        let display = |name: &str| self.get(name).map_or_else(|| name.to_string(), FlagSpec::display_name);
+
+        for flag in self.flags() {
+            if flag.is_required() && flag.default().is_none() && given(flag.name()).is_none() {
+                violations.push(Violation::Missing { flag: flag.display_name() });
+            }
+            let Some(span) = given(flag.name()) else { continue };
+            for other in flag.conflicts() {
+                let reported = violations.iter().any(|violation| {
+                    matches!(violation, Violation::Conflict { flag: first, other: second, .. }
+                        if *first == display(other) && *second == flag.display_name())
+                });
+                if given(other).is_some() && !reported {
+                    violations.push(Violation::Conflict { flag: flag.display_name(), other: display(other), span });
+                }
+            }
+            for required in flag.requirements() {
+                if given(required).is_none() {
+                    violations.push(Violation::Unmet { flag: flag.display_name(), requires: display(required), span });
+                }
+            }
+        }
+
+        for group in self.groups() {
+            let members: Vec<_> = group.members().iter().filter_map(|name| Some((name, given(name)?))).collect();
+            if members.is_empty() {
+                violations.push(Violation::NoneOfGroup {
+                    group: group.name().to_string(),
+                    members: group.members().iter().map(|name| display(name)).collect(),
+                });
+            } else if group.is_exclusive() && members.len() > 1 {
+                violations.push(Violation::ManyOfGroup {
+                    group: group.name().to_string(),
+                    given: members.iter().map(|(name, _)| display(name)).collect(),
+                    span: members.iter().map(|(_, span)| *span).max_by_key(|span| span.start).unwrap_or_default(),
+                });
+            }
+        }
+
+        if let Some(subcommand) = &parsed.subcommand {
+            if let Some(spec) = self.get_subcommand(&subcommand.name) {
+                spec.violations(&subcommand.parsed, violations);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn spec() -> Spec {
+        Spec::new("export")
+            .flag(FlagSpec::new("output").short('o').long("output").takes_value().required())
+            .flag(FlagSpec::new("json").long("json").conflicts_with("pretty"))
+            .flag(FlagSpec::new("pretty").long("pretty").conflicts_with("json"))
+            .flag(FlagSpec::new("key").long("key").takes_value().requires("cert"))
+            .flag(FlagSpec::new("cert").long("cert").takes_value())
+            .flag(FlagSpec::new("csv").long("csv"))
+            .flag(FlagSpec::new("yaml").long("yaml"))
+            .group(Group::exactly_one("format", ["json", "csv", "yaml"]))
+    }
+
+    fn violations(spec: &Spec, input: &str) -> Vec<Violation> {
+        match spec.parse(input) {
+            Ok(_) => Vec::new(),
+            Err(ParseError::Violations { violations, .. }) => violations,
+            Err(error) => panic!("expected violations, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(violations(&spec(), "-o out --json --key k --cert c"), vec![]);
+        assert_eq!(violations(&spec(), "-o out --pretty --no-pretty --json"), vec![]);
+    }
+
+    #[test]
+    fn every_violation() {
+        assert_eq!(violations(&spec(), "--key k --pretty"), vec![
+            Violation::Missing { flag: "--output".to_string() },
+            Violation::Unmet { flag: "--key".to_string(), requires: "--cert".to_string(), span: Span::new(0, 5) },
+            Violation::NoneOfGroup {
+                group: "format".to_string(),
+                members: vec!["--json".to_string(), "--csv".to_string(), "--yaml".to_string()],
+            },
+        ]);
+        assert_eq!(violations(&spec(), "-o a --json --pretty --yaml --csv"), vec![
+            Violation::Conflict { flag: "--json".to_string(), other: "--pretty".to_string(), span: Span::new(5, 11) },
+            Violation::ManyOfGroup {
+                group: "format".to_string(),
+                given: vec!["--json".to_string(), "--csv".to_string(), "--yaml".to_string()],
+                span: Span::new(28, 33),
+            },
+        ]);
+    }
+
+    #[test]
+    fn messages() {
+        let error = spec().parse("--json --pretty --csv").unwrap_err();
+
+        assert_eq!(error.span(), Span::default());
+        assert_eq!(
+            error.to_string(),
+            "missing required flag `--output`\n\
+             `--json` can't be given with `--pretty`\n\
+             only one of `--json`, `--csv` can be given"
+        );
+    }
+
+    #[test]
+    fn fallbacks() {
+        let env = HashMap::from([("OUTPUT".to_string(), "out".to_string()), ("YAML".to_string(), "1".to_string())]);
+        let spec = Spec::new("export")
+            .flag(FlagSpec::new("output").long("output").takes_value().required().env("OUTPUT"))
+            .flag(FlagSpec::new("yaml").long("yaml").env("YAML"))
+            .group(Group::at_least_one("format", ["yaml"]));
+
+        assert!(spec.parse_with_env("", &env).is_ok());
+        assert!(matches!(spec.parse(""), Err(ParseError::Violations { .. })));
+    }
+
+    #[test]
+    fn subcommands() {
+        let spec = Spec::new("git").subcommand(spec());
+
+        assert_eq!(violations(&spec, "export --json -o a"), vec![]);
+        assert_eq!(violations(&spec, "export --json"), vec![Violation::Missing { flag: "--output".to_string() }]);
+    }
+}
@@ -0,0 +1,754 @@
+use crate::help::DEFAULT_WIDTH;
+use crate::suggest;
+use crate::tokenizer::Word;
+use crate::{Flag, Group, ParseError, Parsed, Schema, Span, Written};
+use std::borrow::Cow;
+use std::ffi::OsString;
+
+/// Declares a flag: its canonical name and every way it can be written
+///
+/// ```Rust
+/// let verbose = FlagSpec::new("verbose").short('v').long("verbose").alias("loud");
+/// let output = FlagSpec::new("output").short('o').long("output").takes_value();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlagSpec {
+    name: String,
+    shorts: Vec<char>,
+    longs: Vec<String>,
+    aliases: Vec<String>,
+    takes_value: bool,
+    help: Option<String>,
+    default: Option<String>,
+    value_name: Option<String>,
+    choices: Vec<String>,
+    env: Option<String>,
+    required: bool,
+    conflicts: Vec<String>,
+    requires: Vec<String>,
+}
+
+impl FlagSpec {
+    /// A flag called `name` in parse results, with no way to write it yet
+    pub fn new(name: impl Into<String>) -> FlagSpec {
+        FlagSpec {
+            name: name.into(),
+            shorts: Vec::new(),
+            longs: Vec::new(),
+            aliases: Vec::new(),
+            takes_value: false,
+            help: None,
+            default: None,
+            value_name: None,
+            choices: Vec::new(),
+            env: None,
+            required: false,
+            conflicts: Vec::new(),
+            requires: Vec::new(),
+        }
+    }
+
+    /// Lets the flag be written as `-c`
+    pub fn short(mut self, c: char) -> FlagSpec {
+        self.shorts.push(c);
+        self
+    }
+
+    /// Lets the flag be written as `--name`
+    pub fn long(mut self, name: impl Into<String>) -> FlagSpec {
+        self.longs.push(name.into());
+        self
+    }
+
+    /// Lets the flag be written as `--name`, like [`FlagSpec::long`], for names
+    /// kept for compatibility or convenience
+    pub fn alias(mut self, name: impl Into<String>) -> FlagSpec {
+        self.aliases.push(name.into());
+        self
+    }
+
+    /// Makes the flag take a value
+    pub fn takes_value(mut self) -> FlagSpec {
+        self.takes_value = true;
+        self
+    }
+
+    /// Describes what the flag does
+    pub fn help(mut self, text: impl Into<String>) -> FlagSpec {
+        self.help = Some(text.into());
+        self
+    }
+
+    /// The value the flag has when it isn't given, as it would be written
+    pub fn default_value(mut self, value: impl Into<String>) -> FlagSpec {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Names the value in help text, like `FILE` in `--output <FILE>`
+    pub fn value_name(mut self, name: impl Into<String>) -> FlagSpec {
+        self.value_name = Some(name.into());
+        self
+    }
+
+    /// Limits the values the flag accepts to `choices`
+    pub fn choices<I>(mut self, choices: I) -> FlagSpec
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.choices.extend(choices.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reads the flag from the environment variable `name` when the input
+    /// doesn't give it, see [`Spec::parse_with_env`]
+    pub fn env(mut self, name: impl Into<String>) -> FlagSpec {
+        self.env = Some(name.into());
+        self
+    }
+
+    /// Makes the flag one that must be given, unless it has a default
+    pub fn required(mut self) -> FlagSpec {
+        self.required = true;
+        self
+    }
+
+    /// Forbids giving the flag together with the flag called `name`
+    pub fn conflicts_with(mut self, name: impl Into<String>) -> FlagSpec {
+        self.conflicts.push(name.into());
+        self
+    }
+
+    /// Forbids giving the flag without the flag called `name`
+    pub fn requires(mut self, name: impl Into<String>) -> FlagSpec {
+        self.requires.push(name.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn shorts(&self) -> &[char] {
+        &self.shorts
+    }
+
+    pub fn longs(&self) -> &[String] {
+        &self.longs
+    }
+
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    pub fn needs_value(&self) -> bool {
+        self.takes_value
+    }
+
+    /// The values the flag accepts, empty when it accepts any
+    pub fn allowed_values(&self) -> &[String] {
+        &self.choices
+    }
+
+    /// The environment variable declared with [`FlagSpec::env`]
+    pub fn env_name(&self) -> Option<&str> {
+        self.env.as_deref()
+    }
+
+    pub fn help_text(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// The flags declared with [`FlagSpec::conflicts_with`]
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    /// The flags declared with [`FlagSpec::requires`]
+    pub fn requirements(&self) -> &[String] {
+        &self.requires
+    }
+
+    /// How the value is written in help text: its value name, or else the flag
+    /// name in uppercase
+    pub fn placeholder(&self) -> String {
+        match &self.value_name {
+            Some(name) => name.clone(),
+            None => self.name.to_uppercase().replace('-', "_"),
+        }
+    }
+
+    /// How the flag is written in messages: its first long name, or else its
+    /// first short name
+    pub fn display_name(&self) -> String {
+        match (self.longs.first(), self.shorts.first()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => self.name.clone(),
+        }
+    }
+
+    fn matches(&self, name: &Word, long: bool) -> bool {
+        if long {
+            self.longs.iter().chain(&self.aliases).any(|long| name.is(long))
+        } else {
+            self.shorts.iter().any(|c| name.is(c.encode_utf8(&mut [0; 4])))
+        }
+    }
+}
+
+/// Declares every flag a command accepts
+///
+/// Parsing against a spec reports each flag by its canonical name, however it
+/// was written, so `-v`, `--verbose` and `--loud` below all count as `verbose`.
+/// Flags that aren't declared are errors, and so are flags missing their value
+/// or given one they don't take.
+///
+/// A spec with subcommands expects the first positional to name one of them.
+/// Everything after it is parsed against the subcommand, so each flag is only
+/// accepted at the level it is declared at.
+///
+/// ```Rust
+/// let spec = Spec::new("mytool")
+///     .flag(FlagSpec::new("verbose").short('v').long("verbose").alias("loud"))
+///     .flag(FlagSpec::new("output").short('o').long("output").takes_value());
+///
+/// let parsed = spec.parse("-v --loud -o out.txt")?;
+/// parsed.count("verbose") // 2
+/// parsed.value_of("output") // Some("out.txt")
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    name: String,
+    /// The names of the commands this is a subcommand of, outermost first
+    parents: Vec<String>,
+    about: Option<String>,
+    env_prefix: Option<String>,
+    prefixes: bool,
+    suggestion_distance: Option<usize>,
+    flags: Vec<FlagSpec>,
+    groups: Vec<Group>,
+    subcommands: Vec<Spec>,
+}
+
+impl Spec {
+    pub fn new(name: impl Into<String>) -> Spec {
+        Spec {
+            name: name.into(),
+            parents: Vec::new(),
+            about: None,
+            env_prefix: None,
+            prefixes: false,
+            suggestion_distance: None,
+            flags: Vec::new(),
+            groups: Vec::new(),
+            subcommands: Vec::new(),
+        }
+    }
+
+    /// Describes what the command does
+    pub fn about(mut self, text: impl Into<String>) -> Spec {
+        self.about = Some(text.into());
+        self
+    }
+
+    /// Reads every flag without an [`env`](FlagSpec::env) of its own from an
+    /// environment variable named after it, like `MYTOOL_DRY_RUN` for `dry-run`
+    /// with the prefix `MYTOOL`
+    ///
+    /// Subcommands without a prefix of their own use this one.
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Spec {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Lets long flags be written as any prefix of their names that no other
+    /// flag starts with, like `--verb` for `--verbose`, as GNU `getopt_long`
+    /// does
+    ///
+    /// Prefixes that more than one flag starts with are
+    /// [`ParseError::AmbiguousFlag`] errors. Subcommands do the same.
+    pub fn allow_prefixes(mut self) -> Spec {
+        self.set_prefixes();
+        self
+    }
+
+    /// How many edits away from a declared long flag an unknown long flag can
+    /// be for [`ParseError::UnknownFlag`] to suggest it, 2 unless set. 0 turns
+    /// suggestions off. Subcommands that don't set their own use this one.
+    pub fn suggestion_distance(mut self, max_distance: usize) -> Spec {
+        self.suggestion_distance = Some(max_distance);
+        self.subcommands.iter_mut().for_each(|subcommand| subcommand.inherit_suggestion_distance(max_distance));
+        self
+    }
+
+    pub fn flag(mut self, flag: FlagSpec) -> Spec {
+        self.flags.push(flag);
+        self
+    }
+
+    /// Adds a group of flags, of which one or more must be given
+    pub fn group(mut self, group: Group) -> Spec {
+        self.groups.push(group);
+        self
+    }
+
+    /// Adds a subcommand, selected by its name as the first positional
+    pub fn subcommand(mut self, mut subcommand: Spec) -> Spec {
+        subcommand.add_parent(&self.name);
+        if self.prefixes {
+            subcommand.set_prefixes();
+        }
+        if let Some(max_distance) = self.suggestion_distance {
+            subcommand.inherit_suggestion_distance(max_distance);
+        }
+        self.subcommands.push(subcommand);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn env_prefix_text(&self) -> Option<&str> {
+        self.env_prefix.as_deref()
+    }
+
+    pub fn about_text(&self) -> Option<&str> {
+        self.about.as_deref()
+    }
+
+    /// The full name of the command, like `git remote add`
+    pub fn command_path(&self) -> String {
+        let mut path = self.parents.join(" ");
+        if !path.is_empty() {
+            path.push(' ');
+        }
+        path + &self.name
+    }
+
+    pub fn flags(&self) -> &[FlagSpec] {
+        &self.flags
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn subcommands(&self) -> &[Spec] {
+        &self.subcommands
+    }
+
+    /// The flag declared with the canonical name `name`
+    pub fn get(&self, name: &str) -> Option<&FlagSpec> {
+        self.flags.iter().find(|flag| flag.name == name)
+    }
+
+    /// The subcommand declared with the name `name`
+    pub fn get_subcommand(&self, name: &str) -> Option<&Spec> {
+        self.subcommands.iter().find(|subcommand| subcommand.name == name)
+    }
+
+    /// Returns all flags and positional arguments in a given input, like
+    /// [`parse`](crate::parse) does, with flags named by their canonical names
+    ///
+    /// The result is checked against the rules of the spec, see [`Spec::validate`].
+    pub fn parse<'a>(&self, input: &'a str) -> Result<Parsed<'a>, ParseError> {
+        let parsed = crate::parse_with(input, self)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Returns all flags and positional arguments in arguments that are already
+    /// split, like [`parse_args`](crate::parse_args) does, with flags named by
+    /// their canonical names
+    pub fn parse_args<I>(&self, args: I) -> Result<Parsed<'static>, ParseError>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        let parsed = crate::parse_args_with(args, self)?;
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    fn find(&self, name: &Word, long: bool) -> Option<&FlagSpec> {
+        self.flags.iter().find(|flag| flag.matches(name, long))
+    }
+
+    /// The flag a flag written in the input stands for, with whether it is
+    /// written `--no-<name>` to switch it off
+    ///
+    /// Long flags can also be negations, and prefixes when they are allowed.
+    /// A prefix of `--help` is a [`ParseError::HelpRequested`] error.
+    fn lookup(&self, written: Written, span: Span) -> Result<Option<(&FlagSpec, bool)>, ParseError> {
+        if let Some(flag) = self.find(written.name, written.long) {
+            return Ok(Some((flag, false)));
+        }
+        if !written.long {
+            return Ok(None);
+        }
+        let name = written.name.unquote();
+        let mut candidates: Vec<(String, Option<(&FlagSpec, bool)>)> = Vec::new();
+        for flag in &self.flags {
+            let longs = flag.longs.iter().chain(&flag.aliases);
+            let negations = longs.clone().filter(|_| !flag.takes_value).map(|long| (format!("no-{}", long), true));
+            for (long, negated) in longs.map(|long| (long.clone(), false)).chain(negations) {
+                if long == *name {
+                    return Ok(Some((flag, negated)));
+                }
+                let found = Some((flag, negated));
+                if self.prefixes && long.starts_with(&*name) && !candidates.iter().any(|(_, other)| *other == found) {
+                    candidates.push((long, found));
+                }
+            }
+        }
+        if self.prefixes && self.has_auto_help(true) && "help".starts_with(&*name) {
+            candidates.push(("help".to_string(), None));
+        }
+        match candidates.as_slice() {
+            _ if name.is_empty() => Ok(None),
+            [] => Ok(None),
+            [(_, Some(found))] => Ok(Some(*found)),
+            [(_, None)] => Err(ParseError::HelpRequested { help: self.help_with_width(DEFAULT_WIDTH), span }),
+            _ => Err(ParseError::AmbiguousFlag {
+                flag: written.to_string(),
+                candidates: candidates.into_iter().map(|(long, _)| format!("--{}", long)).collect(),
+                span,
+            }),
+        }
+    }
+
+    /// Whether `--help`, or `-h` when not `long`, prints help, which it does
+    /// unless a flag of the spec is written that way
+    pub(crate) fn has_auto_help(&self, long: bool) -> bool {
+        !self.flags.iter().any(|flag| match long {
+            true => flag.longs.iter().chain(&flag.aliases).any(|long| long == "help"),
+            false => flag.shorts.contains(&'h'),
+        })
+    }
+
+    /// The flags of the spec, with whichever of `-h` and `--help` print help
+    pub(crate) fn flags_with_help(&self) -> Vec<FlagSpec> {
+        let mut flags = self.flags.clone();
+        let mut help = FlagSpec::new("help").help("Print help");
+        if self.has_auto_help(false) {
+            help = help.short('h');
+        }
+        if self.has_auto_help(true) {
+            help = help.long("help");
+        }
+        if self.has_auto_help(false) || self.has_auto_help(true) {
+            flags.push(help);
+        }
+        flags
+    }
+
+    fn set_prefixes(&mut self) {
+        self.prefixes = true;
+        self.subcommands.iter_mut().for_each(Spec::set_prefixes);
+    }
+
+    fn inherit_suggestion_distance(&mut self, max_distance: usize) {
+        if self.suggestion_distance.is_none() {
+            self.suggestion_distance = Some(max_distance);
+            self.subcommands.iter_mut().for_each(|subcommand| subcommand.inherit_suggestion_distance(max_distance));
+        }
+    }
+
+    /// The long flags close to an unknown long flag, written with their dashes
+    fn suggestions(&self, written: Written) -> Vec<String> {
+        if !written.long {
+            return Vec::new();
+        }
+        let longs = self.flags.iter().flat_map(|flag| flag.longs.iter().chain(&flag.aliases)).map(String::as_str);
+        let help = self.has_auto_help(true).then_some("help");
+        let max_distance = self.suggestion_distance.unwrap_or(2);
+        let suggestions = suggest::suggestions(&written.name.unquote(), longs.chain(help), max_distance);
+        suggestions.into_iter().map(|long| format!("--{}", long)).collect()
+    }
+
+    fn add_parent(&mut self, name: &str) {
+        self.parents.insert(0, name.to_string());
+        for subcommand in &mut self.subcommands {
+            subcommand.add_parent(name);
+        }
+    }
+}
+
+impl Schema for Spec {
+    type Error = ParseError;
+
+    fn resolve<'a>(&self, flag: Written<'_, 'a>, span: Span) -> Result<(Cow<'a, str>, bool), ParseError> {
+        match self.lookup(flag, span)? {
+            Some((spec, _)) => Ok((Cow::Owned(spec.name.clone()), spec.takes_value)),
+            None if self.has_auto_help(flag.long) && flag.name.is(if flag.long { "help" } else { "h" }) => {
+                Err(ParseError::HelpRequested { help: self.help_with_width(DEFAULT_WIDTH), span })
+            }
+            None => Err(ParseError::UnknownFlag { flag: flag.to_string(), suggestions: self.suggestions(flag), span }),
+        }
+    }
+
+    fn check(&self, flag: &mut Flag, written: Written, takes_value: bool) -> Result<(), ParseError> {
+        if !takes_value {
+            let negated = matches!(self.lookup(written, flag.span), Ok(Some((_, true))));
+            let on = match &flag.value {
+                None => true,
+                Some(value) => match value.as_str().and_then(parse_bool) {
+                    Some(on) if !negated => on,
+                    _ => {
+                        return Err(ParseError::UnexpectedValue {
+                            flag: written.to_string(),
+                            value: value.to_string(),
+                            span: flag.value_span.unwrap_or(flag.span),
+                        })
+                    }
+                },
+            };
+            flag.value = None;
+            flag.value_span = None;
+            flag.negated = negated || !on;
+            return Ok(());
+        }
+        match (&flag.value, flag.value_span) {
+            (None, _) => Err(ParseError::MissingValue { flag: written.to_string(), span: flag.span }),
+            (Some(value), span) => match self.get(&flag.name) {
+                Some(spec) if !spec.choices.is_empty() && !spec.choices.iter().any(|choice| *value == **choice) => {
+                    Err(ParseError::InvalidValue {
+                        flag: written.to_string(),
+                        value: value.to_string(),
+                        reason: format!("expected one of {}", spec.choices.join(", ")),
+                        span: span.unwrap_or(flag.span),
+                    })
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    fn subcommand(&self, word: &Word, span: Span) -> Result<Option<(String, &Spec)>, ParseError> {
+        if self.subcommands.is_empty() {
+            return Ok(None);
+        }
+        match self.subcommands.iter().find(|subcommand| word.is(&subcommand.name)) {
+            Some(subcommand) => Ok(Some((subcommand.name.clone(), subcommand))),
+            None => Err(ParseError::UnknownSubcommand { name: word.unquote().into_owned(), span }),
+        }
+    }
+}
+
+/// A boolean written as `true`, `false`, `yes`, `no`, `1` or `0`, in any case
+pub(crate) fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Spec {
+        Spec::new("mytool")
+            .flag(FlagSpec::new("verbose").short('v').long("verbose").alias("loud"))
+            .flag(FlagSpec::new("output").short('o').short('O').long("output").long("out").takes_value())
+    }
+
+    #[test]
+    fn canonical_names() {
+        let parsed = spec().parse("-v --verbose --loud -vv build").unwrap();
+
+        assert_eq!(parsed.count("verbose"), 5);
+        assert_eq!(parsed.names(), vec!["verbose"]);
+        assert_eq!(parsed.positional_values(), vec!["build"]);
+    }
+
+    #[test]
+    fn values() {
+        let parsed = spec().parse("-o a -Ob --output=c --out d -vo e").unwrap();
+
+        assert_eq!(parsed.values_of("output").collect::<Vec<_>>(), vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(parsed.count("verbose"), 1);
+    }
+
+    #[test]
+    fn unknown_flags() {
+        assert_eq!(spec().parse("-v --verbos").unwrap_err(), ParseError::UnknownFlag {
+            flag: "--verbos".to_string(),
+            suggestions: vec!["--verbose".to_string()],
+            span: Span::new(3, 11),
+        });
+        assert_eq!(spec().parse("-vx").unwrap_err(), ParseError::UnknownFlag {
+            flag: "-x".to_string(),
+            suggestions: vec![],
+            span: Span::new(2, 3),
+        });
+        assert!(spec().parse("--v").is_err());
+        assert!(spec().parse("-- --v").is_ok());
+    }
+
+    #[test]
+    fn suggestions() {
+        let message = |spec: &Spec, input: &str| spec.parse(input).unwrap_err().to_string();
+
+        assert_eq!(message(&spec(), "--verbos"), "unknown flag `--verbos`, did you mean `--verbose`?");
+        assert_eq!(message(&spec(), "--lout"), "unknown flag `--lout`, did you mean `--loud` or `--out`?");
+        assert_eq!(message(&spec(), "--hepl"), "unknown flag `--hepl`, did you mean `--help`?");
+        assert_eq!(message(&spec(), "--silent"), "unknown flag `--silent`");
+        assert_eq!(message(&spec().suggestion_distance(0), "--verbos"), "unknown flag `--verbos`");
+        assert_eq!(
+            message(&spec().suggestion_distance(4), "--outptu=a"),
+            "unknown flag `--outptu`, did you mean `--output` or `--out`?"
+        );
+
+        let git = Spec::new("git").suggestion_distance(0).subcommand(spec()).subcommand(spec().suggestion_distance(1));
+        assert_eq!(message(&git, "mytool --verbos"), "unknown flag `--verbos`");
+        assert_eq!(message(&git.subcommands()[1], "--verbos"), "unknown flag `--verbos`, did you mean `--verbose`?");
+    }
+
+    #[test]
+    fn missing_values() {
+        assert_eq!(spec().parse("-v --output").unwrap_err(), ParseError::MissingValue {
+            flag: "--output".to_string(),
+            span: Span::new(3, 11),
+        });
+        assert_eq!(spec().parse("-vo").unwrap_err(), ParseError::MissingValue {
+            flag: "-o".to_string(),
+            span: Span::new(2, 3),
+        });
+    }
+
+    #[test]
+    fn unexpected_values() {
+        assert_eq!(spec().parse("--verbose='yes please'").unwrap_err(), ParseError::UnexpectedValue {
+            flag: "--verbose".to_string(),
+            value: "yes please".to_string(),
+            span: Span::new(10, 22),
+        });
+        assert_eq!(
+            spec().parse("--loud=x").unwrap_err().to_string(),
+            "flag `--loud` takes no value, but was given `x`"
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        let tool = spec().flag(FlagSpec::new("version").long("version")).allow_prefixes();
+
+        assert_eq!(tool.parse("--verb --o=a --no-lo").unwrap().names(), vec!["verbose", "output"]);
+        assert!(!tool.parse("--verb --no-verb").unwrap().contains("verbose"));
+        assert!(matches!(tool.parse("--he"), Err(ParseError::HelpRequested { .. })));
+        assert_eq!(tool.parse("--ver").unwrap_err(), ParseError::AmbiguousFlag {
+            flag: "--ver".to_string(),
+            candidates: vec!["--verbose".to_string(), "--version".to_string()],
+            span: Span::new(0, 5),
+        });
+        assert_eq!(
+            tool.parse("-v --v").unwrap_err().to_string(),
+            "flag `--v` is ambiguous, it could be `--verbose`, `--version`"
+        );
+        assert!(matches!(spec().parse("--verb"), Err(ParseError::UnknownFlag { .. })));
+        let commit = Spec::new("commit").flag(FlagSpec::new("all").long("all"));
+        let git = Spec::new("git").allow_prefixes().subcommand(commit);
+        assert!(git.parse("commit --al").unwrap().innermost().contains("all"));
+    }
+
+    #[test]
+    fn negations() {
+        let switch = |input: &str| spec().parse(input).unwrap().switch("verbose");
+
+        assert_eq!(switch("--verbose --no-verbose"), Some(false));
+        assert_eq!(switch("--no-loud -v"), Some(true));
+        assert_eq!(switch(""), None);
+        for (value, on) in [("true", true), ("YES", true), ("1", true), ("false", false), ("no", false), ("0", false)] {
+            assert_eq!(switch(&format!("--verbose={}", value)), Some(on));
+        }
+        let parsed = spec().parse("-vv --verbose=false -v").unwrap();
+        assert_eq!(parsed.count("verbose"), 1);
+        assert_eq!((parsed.flags[2].value.as_ref(), parsed.flags[2].negated), (None, true));
+        assert!(!spec().parse("-v --no-verbose").unwrap().contains("verbose"));
+        assert!(matches!(spec().parse("--no-verbose=true"), Err(ParseError::UnexpectedValue { .. })));
+        assert!(matches!(spec().parse("--no-output"), Err(ParseError::UnknownFlag { .. })));
+    }
+
+    #[test]
+    fn choices() {
+        let spec = Spec::new("ls").flag(FlagSpec::new("color").long("color").takes_value().choices(["auto", "never"]));
+
+        assert_eq!(spec.parse("--color=never").unwrap().value_of("color"), Some("never"));
+        assert_eq!(spec.parse("--color always").unwrap_err(), ParseError::InvalidValue {
+            flag: "--color".to_string(),
+            value: "always".to_string(),
+            reason: "expected one of auto, never".to_string(),
+            span: Span::new(8, 14),
+        });
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        assert_eq!(spec().parse("-o 'oops").unwrap_err(), ParseError::UnterminatedQuote {
+            quote: '\'',
+            span: Span::new(3, 4),
+        });
+    }
+
+    fn git() -> Spec {
+        Spec::new("git")
+            .flag(FlagSpec::new("verbose").short('v'))
+            .subcommand(Spec::new("status").flag(FlagSpec::new("short").short('s')))
+            .subcommand(
+                Spec::new("remote")
+                    .flag(FlagSpec::new("verbose").short('v'))
+                    .subcommand(Spec::new("add").flag(FlagSpec::new("fetch").short('f').long("fetch"))),
+            )
+    }
+
+    #[test]
+    fn subcommands() {
+        let parsed = git().parse("-v remote -v add --fetch origin url").unwrap();
+        let remote = parsed.subcommand.as_deref().unwrap();
+
+        assert_eq!(parsed.subcommand_path(), vec!["remote", "add"]);
+        assert_eq!((remote.name.as_str(), remote.index, remote.span), ("remote", 1, Span::new(3, 9)));
+        assert_eq!(parsed.count("verbose"), 1);
+        assert_eq!(remote.parsed.count("verbose"), 1);
+        assert!(parsed.innermost().contains("fetch"));
+        assert_eq!(parsed.innermost().positional_values(), vec!["origin", "url"]);
+        assert!(git().parse("-v").unwrap().subcommand_path().is_empty());
+    }
+
+    #[test]
+    fn subcommand_levels() {
+        assert_eq!(git().parse("status --fetch").unwrap_err(), ParseError::UnknownFlag {
+            flag: "--fetch".to_string(),
+            suggestions: vec![],
+            span: Span::new(7, 14),
+        });
+        assert!(git().parse("remote -s").is_err());
+        assert!(git().parse("-s status").is_err());
+        assert_eq!(git().parse("-v stats").unwrap_err(), ParseError::UnknownSubcommand {
+            name: "stats".to_string(),
+            span: Span::new(3, 8),
+        });
+        assert_eq!(git().parse("status -- remote").unwrap().innermost().positional_values(), vec!["remote"]);
+    }
+
+    #[test]
+    fn args() {
+        let parsed = spec().parse_args(["--out", "my file", "-v"]).unwrap();
+
+        assert_eq!(parsed.value_of("output"), Some("my file"));
+        assert!(parsed.contains("verbose"));
+    }
+}
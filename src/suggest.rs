@@ -0,0 +1,53 @@
+/// The number of single-character insertions, deletions and substitutions that
+/// turn `a` into `b`
+pub(crate) fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The names at most `max_distance` edits away from `name`, closest first and
+/// otherwise in the order given, each listed once
+pub(crate) fn suggestions<'n>(name: &str, names: impl Iterator<Item = &'n str>, max_distance: usize) -> Vec<&'n str> {
+    let mut close: Vec<(usize, &str)> = Vec::new();
+    for candidate in names {
+        let distance = distance(name, candidate);
+        if distance <= max_distance && !close.iter().any(|(_, other)| *other == candidate) {
+            close.push((distance, candidate));
+        }
+    }
+    close.sort_by_key(|(distance, _)| *distance);
+    close.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("verbose", "verbose"), 0);
+        assert_eq!(distance("verbos", "verbose"), 1);
+        assert_eq!(distance("vrebose", "verbose"), 2);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("größe", "grösse"), 2);
+    }
+
+    #[test]
+    fn closest_first() {
+        let names = ["version", "verbose", "output", "verbose"];
+
+        assert_eq!(suggestions("verbos", names.into_iter(), 2), vec!["verbose"]);
+        assert_eq!(suggestions("versio", names.into_iter(), 4), vec!["version", "verbose"]);
+        assert_eq!(suggestions("verbos", names.into_iter(), 0), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,323 @@
+//! Splits an input into words following POSIX sh word-splitting rules
+//!
+//! Words are separated by unquoted whitespace. Inside a word:
+//!
+//! - `'...'` keeps everything literally up to the next `'`
+//! - `"..."` keeps everything literally except `\` before `$`, `` ` ``, `"`,
+//!   `\` or a newline
+//! - `\` outside of quotes keeps the next character literally
+//! - `\` followed by a newline is a line continuation and is removed
+//!
+//! Words are not copied: each [`Word`] borrows its raw text from the input
+//! and removes the quoting only when asked to.
+
+use crate::Span;
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
+use core::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Error returned when an input can't be split into words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizeError {
+    /// A quote was opened at `offset` and never closed
+    UnterminatedQuote { quote: char, offset: usize },
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::UnterminatedQuote { quote, offset } => {
+                write!(f, "unterminated {} quote starting at byte {}", quote, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TokenizeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+    /// Not quoted at all, quote characters included
+    Literal,
+}
+
+/// A word of the input, borrowed exactly as it is written there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Word<'a> {
+    raw: &'a str,
+    start: usize,
+    quote: Quote,
+}
+
+impl<'a> Word<'a> {
+    /// A word taken exactly as it is, with no quoting to remove, such as an
+    /// argument a shell already split
+    pub fn literal(text: &'a str) -> Word<'a> {
+        Word { raw: text, start: 0, quote: Quote::Literal }
+    }
+
+    /// The word as written in the input, quotes and escapes included
+    pub fn raw(&self) -> &'a str {
+        self.raw
+    }
+
+    /// Where the word is in the input, quotes and escapes included
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.start + self.raw.len())
+    }
+
+    /// The word when it has no quoting to remove, without copying it
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.quote == Quote::Literal || (self.quote == Quote::None && !self.raw.contains(['\'', '"', '\\'])) {
+            Some(self.raw)
+        } else {
+            None
+        }
+    }
+
+    /// The word with its quotes and escapes removed
+    #[cfg(feature = "alloc")]
+    pub fn unquote(&self) -> Cow<'a, str> {
+        match self.as_str() {
+            Some(text) => Cow::Borrowed(text),
+            None => {
+                let mut cursor = self.cursor();
+                let mut text = String::with_capacity(self.raw.len());
+                while let Some(c) = cursor.next_char() {
+                    text.push(c);
+                }
+                Cow::Owned(text)
+            }
+        }
+    }
+
+    /// Whether the word reads `text` once its quoting is removed
+    pub fn is(&self, text: &str) -> bool {
+        let mut cursor = self.cursor();
+        text.chars().all(|c| cursor.next_char() == Some(c)) && cursor.next_char().is_none()
+    }
+
+    pub(crate) fn cursor(&self) -> Cursor<'a> {
+        Cursor { raw: self.raw, start: self.start, pos: 0, quote: self.quote }
+    }
+}
+
+/// Walks the characters of a word with its quoting removed
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor<'a> {
+    raw: &'a str,
+    start: usize,
+    pos: usize,
+    quote: Quote,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn next_char(&mut self) -> Option<char> {
+        loop {
+            let mut chars = self.raw[self.pos..].chars();
+            let c = chars.next()?;
+            let next = chars.next();
+            self.pos += c.len_utf8();
+
+            match (self.quote, c) {
+                (Quote::Literal, c) => return Some(c),
+                (Quote::None, '\'') => self.quote = Quote::Single,
+                (Quote::None, '"') => self.quote = Quote::Double,
+                (Quote::Single, '\'') | (Quote::Double, '"') => self.quote = Quote::None,
+                (Quote::None, '\\') | (Quote::Double, '\\') => match next {
+                    Some('\n') => self.pos += 1,
+                    Some(escaped) if self.quote == Quote::None || "$`\"\\".contains(escaped) => {
+                        self.pos += escaped.len_utf8();
+                        return Some(escaped);
+                    }
+                    _ => return Some('\\'),
+                },
+                _ => return Some(c),
+            }
+        }
+    }
+
+    /// Steps over a whole grapheme cluster, so `é` written as `e` plus a
+    /// combining accent is walked as one
+    ///
+    /// Graphemes don't span quotes or escapes, and escaped characters are
+    /// walked one at a time.
+    pub(crate) fn next_grapheme(&mut self) -> bool {
+        loop {
+            let rest = &self.raw[self.pos..];
+            let special: &[char] = match self.quote {
+                Quote::None => &['\'', '"', '\\'],
+                Quote::Single => &['\''],
+                Quote::Double => &['"', '\\'],
+                Quote::Literal => &[],
+            };
+            let run = &rest[..rest.find(special).unwrap_or(rest.len())];
+            if let Some(grapheme) = run.graphemes(true).next() {
+                self.pos += grapheme.len();
+                return true;
+            }
+            self.quote = match (self.quote, rest.chars().next()) {
+                (_, None) => return false,
+                (Quote::None, Some('\'')) => Quote::Single,
+                (Quote::None, Some('"')) => Quote::Double,
+                (Quote::Single, Some('\'')) | (Quote::Double, Some('"')) => Quote::None,
+                _ => return self.next_char().is_some(),
+            };
+            self.pos += 1;
+        }
+    }
+
+    /// The part of the word that hasn't been walked yet
+    pub(crate) fn rest(&self) -> Word<'a> {
+        Word { raw: &self.raw[self.pos..], start: self.start + self.pos, quote: self.quote }
+    }
+
+    /// The part of the word between `start` and the current position
+    pub(crate) fn since(&self, start: &Cursor<'a>) -> Word<'a> {
+        Word { raw: &self.raw[start.pos..self.pos], start: self.start + start.pos, quote: start.quote }
+    }
+}
+
+/// Iterator over the words of an input
+///
+/// Stops after reporting an unterminated quote.
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str) -> Tokenizer<'a> {
+        Tokenizer { input, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Word<'a>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.input[self.pos..];
+            self.pos += rest.len() - rest.trim_start_matches(|c: char| c.is_whitespace()).len();
+            if !self.input[self.pos..].starts_with("\\\n") {
+                break;
+            }
+            self.pos += 2;
+        }
+
+        let start = self.pos;
+        let mut quote = Quote::None;
+        let mut opened_at = start;
+        let mut chars = self.input[start..].char_indices().map(|(i, c)| (start + i, c));
+
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                (Quote::None, c) if c.is_whitespace() => break,
+                (Quote::None, '\'') => (quote, opened_at) = (Quote::Single, i),
+                (Quote::None, '"') => (quote, opened_at) = (Quote::Double, i),
+                (Quote::Single, '\'') | (Quote::Double, '"') => quote = Quote::None,
+                (Quote::None, '\\') | (Quote::Double, '\\') => {
+                    if let Some((i, escaped)) = chars.next() {
+                        self.pos = i + escaped.len_utf8();
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            self.pos = i + c.len_utf8();
+        }
+
+        match quote {
+            _ if start == self.input.len() => None,
+            Quote::None | Quote::Literal => Some(Ok(Word { raw: &self.input[start..self.pos], start, quote })),
+            Quote::Single | Quote::Double => {
+                self.pos = self.input.len();
+                let quote = if quote == Quote::Single { '\'' } else { '"' };
+                Some(Err(TokenizeError::UnterminatedQuote { quote, offset: opened_at }))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<String> {
+        Tokenizer::new(input).map(|word| word.unwrap().unquote().into_owned()).collect()
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(words("  a\tb\n c  "), vec!["a", "b", "c"]);
+        assert!(words(" \t\n").is_empty());
+    }
+
+    #[test]
+    fn single_quotes() {
+        assert_eq!(words(r#"'hello world' 'a\b"c' x'y'z ''"#), vec!["hello world", r#"a\b"c"#, "xyz", ""]);
+    }
+
+    #[test]
+    fn double_quotes() {
+        assert_eq!(words(r#""hello world" "a\"b\\c\$d" "a\b" "it's""#), vec![
+            "hello world",
+            r#"a"b\c$d"#,
+            r"a\b",
+            "it's",
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(words(r"my\ file \'a\' \\ a\"), vec!["my file", "'a'", r"\", r"a\"]);
+    }
+
+    #[test]
+    fn line_continuations() {
+        assert_eq!(words("a\\\nb \\\n c \"d\\\ne\""), vec!["ab", "c", "de"]);
+        assert_eq!(words(&(" \\\n".repeat(100_000) + "-a")), vec!["-a"]);
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        let mut words = Tokenizer::new(r#"a "b c"#);
+
+        assert_eq!(words.next().unwrap().unwrap().raw(), "a");
+        assert_eq!(words.next(), Some(Err(TokenizeError::UnterminatedQuote { quote: '"', offset: 2 })));
+        assert_eq!(words.next(), None);
+
+        let mut words = Tokenizer::new("'a");
+        assert_eq!(words.next(), Some(Err(TokenizeError::UnterminatedQuote { quote: '\'', offset: 0 })));
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<_> = Tokenizer::new(r#" ab  "c d"\ e"#).map(|word| word.unwrap().span()).collect();
+
+        assert_eq!(spans, vec![Span::new(1, 3), Span::new(5, 13)]);
+    }
+
+    #[test]
+    fn unquoted_words_are_borrowed() {
+        let word = Tokenizer::new("plain").next().unwrap().unwrap();
+
+        assert!(matches!(word.unquote(), Cow::Borrowed("plain")));
+        assert!(word.is("plain"));
+        assert!(Tokenizer::new("'pl'ain").next().unwrap().unwrap().is("plain"));
+    }
+
+    #[test]
+    fn literal_words() {
+        let word = Word::literal(r#"it's "a\b""#);
+
+        assert_eq!(word.unquote(), r#"it's "a\b""#);
+        assert!(matches!(word.unquote(), Cow::Borrowed(_)));
+    }
+}